@@ -0,0 +1,41 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use super::config::Adapter;
+
+// ADAPTER CONTENT
+// % of reads containing the first 12 bases of common adapters anywhere (FastQC adapter list)
+// The list can be replaced with [[adapters]] in rsfqc.toml
+
+pub const ADAPTERS: [(&str, &str); 7] = [
+    ("Illumina Universal Adapter", "AGATCGGAAGAG"),
+    ("Illumina Small RNA 3' Adapter", "TGGAATTCTCGG"),
+    ("Illumina Small RNA 5' Adapter", "GATCGTCGGACT"),
+    ("Nextera Transposase Sequence", "CTGTCTCTTATA"),
+    ("SOLiD Small RNA Adapter", "CGCCTTGGCCGT"),
+    ("PolyA", "AAAAAAAAAAAA"),
+    ("PolyG", "GGGGGGGGGGGG"),
+];
+
+#[derive(Debug,Clone,Default,Serialize)]
+pub struct AdapterReport {
+    pub adapters: Vec<(String, f32)>, // name and % of reads
+    pub max_percent: f32,
+}
+
+/// % of reads containing each adapter
+pub fn adapter_report(sequences: &[String], adapter_list: &[Adapter]) -> AdapterReport {
+    if sequences.is_empty() {
+        return AdapterReport::default()
+    }
+    let adapters: Vec<(String, f32)> = adapter_list.iter()
+    .map(|adapter| {
+        let sequence = adapter.sequence.to_ascii_uppercase();
+        let reads = sequences.par_iter().filter(|x| x.contains(&sequence)).count();
+        (adapter.name.clone(), reads as f32 / sequences.len() as f32 * 100.0)
+    })
+    .collect();
+    AdapterReport {
+        max_percent: adapters.iter().map(|x| x.1).fold(0.0, f32::max),
+        adapters,
+    }
+}
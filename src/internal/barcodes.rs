@@ -0,0 +1,220 @@
+use std::{fs::File, io::{BufRead, BufReader}};
+use itertools::Itertools;
+use serde::Serialize;
+use super::illumina::IlluminaHeader;
+
+/// Expected index of a sample from a sample sheet
+#[derive(Debug,Clone)]
+pub struct ExpectedIndex {
+    pub sample: String,
+    pub i7: String,
+    pub i5: Option<String>,
+}
+
+/// Index composition of the sampled reads
+#[derive(Debug,Clone,Default,Serialize)]
+pub struct IndexReport {
+    pub reads_with_index: usize,
+    pub reads_with_n: usize,
+    pub is_dual: bool,
+    pub top_indices: Vec<(String, usize)>, // full index, i7+i5 when dual
+    pub top_i7: Vec<(String, usize)>,
+    pub top_i5: Vec<(String, usize)>,
+    // Only when a sample sheet is given
+    pub per_sample: Vec<(String, usize)>,
+    pub perfect_match: usize,
+    pub one_mismatch: usize,
+    pub hopped: usize,
+    pub undetermined: usize,
+    pub i5_reverse_complemented: bool,
+}
+
+/// Read expected indices from a sample sheet
+/// Works with Illumina sample sheets ([Data] section) and plain CSV with a header line
+/// Needs an "index" column, "index2" is optional
+/// The same index listed on several lines (one per lane) is kept once
+pub fn read_sample_sheet(filepath: &String) -> Option<Vec<ExpectedIndex>> {
+    let reader = BufReader::new(File::open(filepath).ok()?);
+    let lines: Vec<String> = reader.lines().map_while(Result::ok).collect();
+
+    // Header line is the first line after [Data] or the first line if there is no [Data] section
+    let start = lines.iter().position(|x| x.trim().eq_ignore_ascii_case("[Data]")).map(|x| x + 1).unwrap_or(0);
+    let header: Vec<String> = lines.get(start)?.split(',').map(|x| x.trim().to_lowercase()).collect();
+    let index_column = header.iter().position(|x| x == "index")?;
+    let index2_column = header.iter().position(|x| x == "index2");
+    let sample_column = header.iter().position(|x| x == "sample_id" || x == "sample").unwrap_or(0);
+
+    let mut expected: Vec<ExpectedIndex> = Vec::new();
+    for line in lines.iter().skip(start + 1) {
+        // Stop at the next section
+        if line.starts_with('[') {break}
+        let fields: Vec<&str> = line.split(',').map(|x| x.trim()).collect();
+        let Some(i7) = fields.get(index_column).filter(|x| !x.is_empty()) else {continue};
+        expected.push(ExpectedIndex {
+            sample: fields.get(sample_column).unwrap_or(&"").to_string(),
+            i7: i7.to_uppercase(),
+            i5: index2_column.and_then(|x| fields.get(x)).filter(|x| !x.is_empty()).map(|x| x.to_uppercase()),
+        });
+    }
+    Some(expected.into_iter().unique_by(|x| (x.i7.clone(), x.i5.clone())).collect())
+}
+
+/// Reverse complement of a DNA sequence
+pub fn reverse_complement(sequence: &str) -> String {
+    sequence.chars().rev().map(|x| match x {
+        'A' => 'T', 'T' => 'A', 'C' => 'G', 'G' => 'C', other => other
+    }).collect()
+}
+
+/// Number of mismatches between two sequences - N always counts as a mismatch
+/// Different lengths are compared on the shortest one, extra bases are mismatches
+pub fn hamming_distance(a: &str, b: &str) -> usize {
+    let common = a.chars().zip(b.chars()).filter(|(x, y)| x != y || *x == 'N').count();
+    common + a.len().abs_diff(b.len())
+}
+
+/// Samples whose index is at most one mismatch away
+fn close_samples(observed: &str, expected: &[&str]) -> Vec<(usize, usize)> {
+    expected.iter().enumerate()
+    .map(|(sample, x)| (sample, hamming_distance(observed, x)))
+    .filter(|(_, distance)| *distance <= 1)
+    .collect()
+}
+
+/// Most frequent items with their counts
+fn top_counts(items: Vec<&str>, number_to_keep: usize) -> Vec<(String, usize)> {
+    let mut counts: Vec<(String, usize)> = items.into_iter().counts().into_iter().map(|(x, count)| (x.to_string(), count)).collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts.truncate(number_to_keep);
+    counts
+}
+
+/// Assign reads to samples, returns (per sample counts, perfect, one mismatch, hopped, undetermined)
+fn assign_to_samples(indices: &[&str], expected: &[ExpectedIndex], i5_reverse_complemented: bool) -> (Vec<usize>, usize, usize, usize, usize) {
+    let expected_i7: Vec<&str> = expected.iter().map(|x| x.i7.as_str()).collect();
+    let expected_i5: Vec<String> = expected.iter()
+    .map(|x| x.i5.clone().map(|i5| if i5_reverse_complemented {reverse_complement(&i5)} else {i5}).unwrap_or_default())
+    .collect();
+    let expected_i5: Vec<&str> = expected_i5.iter().map(|x| x.as_str()).collect();
+    let has_i5 = expected.iter().any(|x| x.i5.is_some());
+
+    let mut per_sample = vec![0; expected.len()];
+    let (mut perfect, mut one_mismatch, mut hopped, mut undetermined) = (0, 0, 0, 0);
+    for index in indices {
+        let mut parts = index.splitn(2, '+');
+        let i7 = parts.next().unwrap_or("");
+        let i5 = parts.next().unwrap_or("");
+
+        let close_i7 = close_samples(i7, &expected_i7);
+        let close_i5 = if has_i5 {close_samples(i5, &expected_i5)} else {close_i7.iter().map(|(x, _)| (*x, 0)).collect()};
+
+        // Both parts point to one and the same sample
+        let matching: Vec<(usize, usize)> = close_i7.iter()
+        .filter_map(|(sample, d7)| close_i5.iter().find(|(x, _)| x == sample).map(|(_, d5)| (*sample, d7 + d5)))
+        .collect();
+
+        match matching.as_slice() {
+            [(sample, 0)] => {perfect += 1; per_sample[*sample] += 1},
+            [(sample, _)] => {one_mismatch += 1; per_sample[*sample] += 1},
+            [] if close_i7.len() == 1 && close_i5.len() == 1 => hopped += 1,
+            _ => undetermined += 1,
+        }
+    }
+    (per_sample, perfect, one_mismatch, hopped, undetermined)
+}
+
+/// Index composition from the Illumina read names
+pub fn index_report(headers: &[Option<IlluminaHeader>], expected: &[ExpectedIndex]) -> IndexReport {
+    let indices: Vec<&str> = headers.iter()
+    .flatten()
+    .filter_map(|x| x.index.as_deref())
+    .filter(|x| !x.is_empty())
+    .collect();
+
+    let mut report = IndexReport {
+        reads_with_index: indices.len(),
+        reads_with_n: indices.iter().filter(|x| x.contains('N')).count(),
+        is_dual: indices.iter().any(|x| x.contains('+')),
+        ..Default::default()
+    };
+    if indices.is_empty() {
+        return report
+    }
+
+    report.top_indices = top_counts(indices.clone(), 10);
+    if report.is_dual {
+        report.top_i7 = top_counts(indices.iter().map(|x| x.split('+').next().unwrap_or("")).collect(), 10);
+        report.top_i5 = top_counts(indices.iter().map(|x| x.split('+').nth(1).unwrap_or("")).collect(), 10);
+    }
+
+    if !expected.is_empty() {
+        // i5 can be given in either orientation depending on the instrument, keep the one that matches best
+        let forward = assign_to_samples(&indices, expected, false);
+        let reverse = assign_to_samples(&indices, expected, true);
+        report.i5_reverse_complemented = reverse.1 + reverse.2 > forward.1 + forward.2;
+        let (per_sample, perfect, one_mismatch, hopped, undetermined) = if report.i5_reverse_complemented {reverse} else {forward};
+
+        // A sample can have several index pairs
+        for (x, count) in expected.iter().zip(per_sample) {
+            match report.per_sample.iter_mut().find(|(sample, _)| *sample == x.sample) {
+                Some(existing) => existing.1 += count,
+                None => report.per_sample.push((x.sample.clone(), count)),
+            }
+        }
+        report.perfect_match = perfect;
+        report.one_mismatch = one_mismatch;
+        report.hopped = hopped;
+        report.undetermined = undetermined;
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(sample: &str, i7: &str, i5: &str) -> ExpectedIndex {
+        ExpectedIndex {sample: sample.to_string(), i7: i7.to_string(), i5: Some(i5.to_string())}
+    }
+
+    fn expected() -> Vec<ExpectedIndex> {
+        vec![sample("A", "ACGTACGT", "TTGACGAA"), sample("B", "GGTTAACC", "CAGTCAGT")]
+    }
+
+    #[test]
+    fn perfect_and_one_mismatch_matches() {
+        let (per_sample, perfect, one_mismatch, hopped, undetermined) = assign_to_samples(&["ACGTACGT+TTGACGAA", "ACGTACGA+TTGACGAA", "GGTTAACC+CAGTCAGN"], &expected(), false);
+        assert_eq!(per_sample, vec![2, 1]);
+        assert_eq!((perfect, one_mismatch, hopped, undetermined), (1, 2, 0, 0));
+    }
+
+    #[test]
+    fn hopped_and_undetermined_reads() {
+        // Valid i7 of A with the valid i5 of B
+        let (per_sample, perfect, _, hopped, undetermined) = assign_to_samples(&["ACGTACGT+CAGTCAGT", "AAAAAAAA+GGGGGGGG", "ACGTACGT+TTGATTTT"], &expected(), false);
+        assert_eq!(per_sample, vec![0, 0]);
+        assert_eq!((perfect, hopped, undetermined), (0, 1, 2));
+    }
+
+    #[test]
+    fn reverse_complemented_i5() {
+        // Sequenced i5 is the reverse complement of the sample sheet one
+        let reads: Vec<String> = expected().iter().map(|x| format!("{}+{}", x.i7, reverse_complement(x.i5.as_ref().unwrap()))).collect();
+        let reads: Vec<&str> = reads.iter().map(|x| x.as_str()).collect();
+        assert_eq!(assign_to_samples(&reads, &expected(), false).1, 0);
+        assert_eq!(assign_to_samples(&reads, &expected(), true).1, 2);
+        assert_eq!(reverse_complement("AACGTN"), "NACGTT");
+    }
+
+    #[test]
+    fn reads_the_data_section_of_a_sample_sheet() {
+        let path = std::env::temp_dir().join(format!("rsfqc_sheet_{}.csv", std::process::id()));
+        std::fs::write(&path, "[Header]\nIEMFileVersion,4\n\n[Data]\nLane,Sample_ID,index,index2\n1,A,acgtacgt,TTGGCCAA\n2,A,ACGTACGT,TTGGCCAA\n1,B,GGTTAACC,\n\n[Settings]\nAdapter,CTGTCTCTTATA\n").unwrap();
+        let sheet = read_sample_sheet(&path.to_str().unwrap().to_string()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(sheet.len(), 2);
+        assert_eq!((sheet[0].sample.as_str(), sheet[0].i7.as_str(), sheet[0].i5.as_deref()), ("A", "ACGTACGT", Some("TTGGCCAA")));
+        assert_eq!((sheet[1].sample.as_str(), sheet[1].i5.as_deref()), ("B", None));
+    }
+}
@@ -0,0 +1,64 @@
+// QUALITY BINNING
+// Recent instruments only report a handful of quality values
+
+/// Known binning schemes - name and quality values
+const KNOWN_SCHEMES: [(&str, &[u32]); 3] = [
+    ("Illumina RTA3 4 levels (NovaSeq 6000, iSeq, MiniSeq)", &[2, 12, 23, 37]),
+    ("Illumina 8 levels (HiSeq 2500 / 4000 / X)", &[2, 6, 15, 22, 27, 33, 37, 40]),
+    ("Illumina NextSeq 500/550", &[14, 21, 27, 32, 36]),
+];
+
+/// Anything with this many distinct values or fewer is considered binned
+const MAX_BINNED_ALPHABET: usize = 8;
+
+/// Distinct quality values with the number of bases, from the base quality histogram
+pub fn quality_alphabet(quality_histogram: &[u64]) -> Vec<(u32, u64)> {
+    quality_histogram.iter()
+    .enumerate()
+    .filter(|(_, count)| **count > 0)
+    .map(|(q, count)| (q as u32, *count))
+    .collect()
+}
+
+/// Name of the binning scheme, None when qualities are not binned
+pub fn detect_binning(alphabet: &[(u32, u64)]) -> Option<String> {
+    // A single value (PacBio CLR, all Q0...) is not a binning scheme
+    if alphabet.len() < 2 || alphabet.len() > MAX_BINNED_ALPHABET {
+        return None
+    }
+    let values: Vec<u32> = alphabet.iter().map(|x| x.0).collect();
+    // All observed values are in the scheme and most of the scheme is observed
+    for (name, scheme) in KNOWN_SCHEMES {
+        let observed = values.iter().filter(|x| scheme.contains(x)).count();
+        if observed == values.len() && observed * 2 >= scheme.len() {
+            return Some(name.to_string())
+        }
+    }
+    Some(format!("Unknown scheme ({} levels)", values.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alphabet(values: &[u32]) -> Vec<(u32, u64)> {
+        values.iter().map(|x| (*x, 100)).collect()
+    }
+
+    #[test]
+    fn known_and_unknown_schemes() {
+        assert_eq!(detect_binning(&alphabet(&[2, 12, 23, 37])).as_deref(), Some(KNOWN_SCHEMES[0].0));
+        // Most of the scheme is enough
+        assert_eq!(detect_binning(&alphabet(&[2, 15, 27, 33, 37])).as_deref(), Some(KNOWN_SCHEMES[1].0));
+        assert_eq!(detect_binning(&alphabet(&[3, 20, 35])).as_deref(), Some("Unknown scheme (3 levels)"));
+        assert_eq!(detect_binning(&alphabet(&(0..42).collect::<Vec<u32>>())), None);
+        assert_eq!(detect_binning(&[]), None);
+        assert_eq!(detect_binning(&alphabet(&[0])), None);
+        assert_eq!(detect_binning(&alphabet(&[7, 20])).as_deref(), Some("Unknown scheme (2 levels)"));
+    }
+
+    #[test]
+    fn alphabet_skips_missing_values() {
+        assert_eq!(quality_alphabet(&[0, 5, 0, 7]), vec![(1, 5), (3, 7)]);
+    }
+}
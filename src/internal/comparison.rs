@@ -0,0 +1,83 @@
+use std::io::IsTerminal;
+use textplots::{Chart, ColorPlot, Plot, Shape};
+use rgb::RGB8;
+use super::fastq::FqObject;
+use super::func::sep;
+
+// MULTI MODE COMPARISON
+// Terminal charts comparing all processed files: mean quality at each position overlaid
+// (one color per file, short and long reads on separate charts so each keeps its own x range),
+// then file size and duplication per file as bars (x = file number)
+
+/// Line colors, cycled when there are more files
+const COLORS: [RGB8; 10] = [
+    RGB8 {r: 31, g: 119, b: 180}, RGB8 {r: 255, g: 127, b: 14}, RGB8 {r: 44, g: 160, b: 44}, RGB8 {r: 214, g: 39, b: 40},
+    RGB8 {r: 148, g: 103, b: 189}, RGB8 {r: 140, g: 86, b: 75}, RGB8 {r: 227, g: 119, b: 194}, RGB8 {r: 127, g: 127, b: 127},
+    RGB8 {r: 188, g: 189, b: 34}, RGB8 {r: 23, g: 190, b: 207},
+];
+
+/// Colored legend mark (plain when not printing to a terminal)
+fn legend_mark(color: RGB8) -> String {
+    if std::io::stdout().is_terminal() {format!("\x1b[38;2;{};{};{}m■\x1b[0m", color.r, color.g, color.b)} else {"■".to_string()}
+}
+
+/// Outline of one bar per file, x = file number
+fn bars(values: &[f32]) -> Vec<(f32, f32)> {
+    values.iter().enumerate().flat_map(|(i, y)| [(i as f32 + 0.6, 0.0), (i as f32 + 0.6, *y), (i as f32 + 1.4, *y), (i as f32 + 1.4, 0.0)]).collect()
+}
+
+/// Display the comparison charts of all processed files
+pub fn comparison_charts(input: &[FqObject], width: u32, height: u32) {
+    let files: Vec<&FqObject> = input.iter().filter(|x| x.processed).collect();
+    if files.len() < 2 {
+        return
+    }
+    sep();
+    println!("COMPARISON OF {} FILES", files.len());
+    sep();
+    println!("#\tFile\tFile Size\tDuplicated\tMedian Q\tOutliers");
+    for (i, fq) in files.iter().enumerate() {
+        let duplicated = if fq.modules.duplication {format!("{:.2}%", 100.0 - fq.duplication_levels)} else {"NA".to_string()};
+        let outliers = fq.outliers.iter().flatten().map(|x| format!("{} {}", x.metric, x.direction())).collect::<Vec<String>>().join("; ");
+        println!("{}\t{} {}\t{:.1} MB\t{}\t{:.2}\t{}", i + 1, legend_mark(COLORS[i % COLORS.len()]), fq.sample, fq.file_bytes as f64 / 1e6, duplicated, fq.qual_median, outliers);
+    }
+    if files.len() > COLORS.len() {println!("Colors repeat every {} files", COLORS.len())};
+
+    // Mean quality at each position - same coverage filter as single mode
+    // One chart per read type, a single long read file would squeeze the short read lines
+    for long_reads in [false, true] {
+        let group: Vec<(usize, &&FqObject)> = files.iter().enumerate().filter(|(_, fq)| fq.platform.is_long_reads() == long_reads).collect();
+        if group.is_empty() {
+            continue
+        }
+        let points: Vec<Vec<(f32, f32)>> = group.iter()
+        .map(|(_, fq)| {
+            let min_reads = if fq.platform.has_variable_length() {fq.min_reads_per_bin} else {1};
+            fq.position_qualities.iter().filter(|x| x.2 >= min_reads).map(|x| (x.0 as f32, x.1)).collect()
+        })
+        .collect();
+        let lines: Vec<Shape> = points.iter().map(|x| Shape::Lines(x)).collect();
+        let last_position = points.iter().flat_map(|x| x.last().map(|x| x.0)).fold(2.0, f32::max);
+        let read_type = if long_reads {"long read"} else {"short read"};
+        println!("\ny = Mean quality score at each position, one line per {} file (colors as in the table above)", read_type);
+        let mut chart = Chart::new(width, height, 1.0, last_position);
+        lines.iter().zip(&group)
+        .fold(&mut chart, |chart, (line, (i, _))| chart.linecolorplot(line, COLORS[i % COLORS.len()]))
+        .display();
+    }
+
+    // File size (reads are sampled, the size on disk stands for the yield) and duplication per file
+    let megabytes: Vec<f32> = files.iter().map(|fq| fq.file_bytes as f32 / 1e6).collect();
+    println!("\ny = File size (MB), x = file number");
+    Chart::new_with_y_range(width, height, 0.0, files.len() as f32 + 1.0, 0.0, megabytes.iter().cloned().fold(1.0, f32::max))
+    .lineplot(&Shape::Lines(&bars(&megabytes)))
+    .display();
+
+    if files.iter().any(|fq| fq.modules.duplication) {
+        let duplication: Vec<f32> = files.iter().map(|fq| if fq.modules.duplication {100.0 - fq.duplication_levels} else {0.0}).collect();
+        println!("\ny = % duplicated reads (first 50 bases), x = file number");
+        Chart::new_with_y_range(width, height, 0.0, files.len() as f32 + 1.0, 0.0, duplication.iter().cloned().fold(1.0, f32::max))
+        .lineplot(&Shape::Lines(&bars(&duplication)))
+        .display();
+    }
+}
@@ -0,0 +1,166 @@
+use rayon::prelude::*;
+use itertools::Itertools;
+use serde::Serialize;
+use super::func::sorted_quantile;
+
+// SEQUENCE COMPLEXITY
+// DUST score (as in prinseq): for each 64 bp window (step 32), sum of c*(c-1)/2 over the 64 trinucleotides
+// divided by (number of trinucleotides - 1), scaled from 0 (random) to 100 (homopolymer of the same length), averaged over windows
+// Entropy: Shannon entropy of the trinucleotides, scaled from 0 (homopolymer) to 1 (all trinucleotides equally frequent)
+
+const DUST_WINDOW: usize = 64;
+const DUST_STEP: usize = 32;
+
+/// Low complexity if the DUST score is above this (prinseq default)
+pub const DUST_THRESHOLD: f32 = 7.0;
+
+#[derive(Debug,Clone,Default,Serialize)]
+pub struct ComplexityReport {
+    pub low_complexity_reads: usize,
+    pub low_complexity_percent: f32,
+    pub dust_mean: f32,
+    pub dust_median: f32,
+    pub entropy_mean: f32,
+    pub entropy_median: f32,
+    pub dust_histogram: Vec<u64>, // index is the rounded DUST score (0 - 100)
+    pub top_low_complexity: Vec<(String, usize)>,
+}
+
+/// Index of a trinucleotide (0 - 63), None if it contains anything else than ACGT
+fn trinucleotide_index(bases: &[u8]) -> Option<usize> {
+    bases.iter().try_fold(0, |acc, base| {
+        let value = match base {b'A' | b'a' => 0, b'C' | b'c' => 1, b'G' | b'g' => 2, b'T' | b't' => 3, _ => return None};
+        Some(acc * 4 + value)
+    })
+}
+
+/// Trinucleotide counts of a sequence and the number of valid trinucleotides
+fn trinucleotide_counts(sequence: &[u8]) -> ([u32; 64], u32) {
+    let mut counts = [0u32; 64];
+    let mut total = 0;
+    for window in sequence.windows(3) {
+        if let Some(index) = trinucleotide_index(window) {
+            counts[index] += 1;
+            total += 1;
+        }
+    }
+    (counts, total)
+}
+
+/// DUST score of one window (or a whole read shorter than a window) scaled 0 - 100
+fn window_dust(window: &[u8]) -> f32 {
+    let (counts, total) = trinucleotide_counts(window);
+    if total < 2 {
+        return 0.0
+    }
+    let score: f32 = counts.iter().map(|c| (c * c.saturating_sub(1)) as f32 / 2.0).sum::<f32>() / (total - 1) as f32;
+    // A homopolymer window of n bases scores (n - 2) / 2 - 31 for a full window
+    score / ((window.len() - 2) as f32 / 2.0) * 100.0
+}
+
+/// DUST score of a read, mean of the windows
+pub fn dust_score(sequence: &str) -> f32 {
+    let sequence = sequence.as_bytes();
+    if sequence.len() <= DUST_WINDOW {
+        return window_dust(sequence)
+    }
+    let starts: Vec<usize> = (0..=sequence.len() - DUST_WINDOW).step_by(DUST_STEP).collect();
+    starts.iter().map(|start| window_dust(&sequence[*start..*start + DUST_WINDOW])).sum::<f32>() / starts.len() as f32
+}
+
+/// Trinucleotide Shannon entropy of a read scaled 0 - 1
+pub fn trinucleotide_entropy(sequence: &str) -> f32 {
+    let (counts, total) = trinucleotide_counts(sequence.as_bytes());
+    if total < 2 {
+        return 0.0
+    }
+    let entropy: f32 = counts.iter()
+    .filter(|x| **x > 0)
+    .map(|x| {
+        let p = *x as f32 / total as f32;
+        -p * p.log2()
+    })
+    .sum();
+    // Maximum is reached when every trinucleotide is seen the same number of times
+    entropy / (total.min(64) as f32).log2()
+}
+
+/// Complexity of all reads
+pub fn complexity_report(sequences: &[String]) -> ComplexityReport {
+    let scores: Vec<(f32, f32)> = sequences.par_iter().map(|x| (dust_score(x), trinucleotide_entropy(x))).collect();
+    if scores.is_empty() {
+        return ComplexityReport::default()
+    }
+
+    let mut dust: Vec<f32> = scores.iter().map(|x| x.0).collect();
+    let mut entropy: Vec<f32> = scores.iter().map(|x| x.1).collect();
+    let mut dust_histogram: Vec<u64> = vec![0; 101];
+    for x in &dust {dust_histogram[(x.round() as usize).min(100)] += 1};
+
+    // Most frequent low complexity sequences
+    let top_low_complexity: Vec<(String, usize)> = sequences.iter()
+    .zip(&scores)
+    .filter(|(_, (x, _))| *x > DUST_THRESHOLD)
+    .map(|(x, _)| x.as_str())
+    .counts()
+    .into_iter()
+    .sorted_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)))
+    .take(10)
+    .map(|(x, count)| (x.to_string(), count))
+    .collect();
+    let low_complexity_reads = dust.iter().filter(|x| **x > DUST_THRESHOLD).count();
+
+    dust.sort_by(|a, b| a.total_cmp(b));
+    entropy.sort_by(|a, b| a.total_cmp(b));
+
+    ComplexityReport {
+        low_complexity_reads,
+        low_complexity_percent: low_complexity_reads as f32 / sequences.len() as f32 * 100.0,
+        dust_mean: dust.iter().sum::<f32>() / dust.len() as f32,
+        dust_median: sorted_quantile(&dust, 0.5),
+        entropy_mean: entropy.iter().sum::<f32>() / entropy.len() as f32,
+        entropy_median: sorted_quantile(&entropy, 0.5),
+        dust_histogram,
+        top_low_complexity,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const READ: &str = "GATTTGGGGTTCAAAGCAGTATCGATCAAATAGTAAATCCATTTGTTCAACTCACAGTTTACGGTCAGCTTAGCCAGTCGATTGCCATGACTAGGCTAAC";
+
+    #[test]
+    fn dust_scores_homopolymers_highest() {
+        assert_eq!(dust_score(&"A".repeat(100)), 100.0);
+        assert_eq!(dust_score(&"A".repeat(64)), 100.0);
+        // Short reads are scaled on their own length
+        assert_eq!(dust_score(&"A".repeat(30)), 100.0);
+        assert!(dust_score(&READ[..40]) < DUST_THRESHOLD);
+        assert!(dust_score(&"CA".repeat(50)) > DUST_THRESHOLD);
+        assert!(dust_score(READ) < DUST_THRESHOLD);
+        // Too short for a trinucleotide pair
+        assert_eq!(dust_score("ACG"), 0.0);
+    }
+
+    #[test]
+    fn dust_ignores_n() {
+        assert_eq!(dust_score(&"N".repeat(100)), 0.0);
+    }
+
+    #[test]
+    fn entropy_scaled_from_zero_to_one() {
+        assert_eq!(trinucleotide_entropy(&"A".repeat(100)), 0.0);
+        let entropy = trinucleotide_entropy(READ);
+        assert!(entropy > 0.8 && entropy <= 1.0);
+    }
+
+    #[test]
+    fn report_counts_low_complexity_reads() {
+        let reads = vec!["A".repeat(100), READ.to_string(), READ.to_string(), "CA".repeat(50)];
+        let report = complexity_report(&reads);
+        assert_eq!(report.low_complexity_reads, 2);
+        assert_eq!(report.low_complexity_percent, 50.0);
+    }
+}
@@ -0,0 +1,251 @@
+use std::path::PathBuf;
+use serde::Deserialize;
+use super::verdict::Thresholds;
+use super::adapters::ADAPTERS;
+use super::summary::{SUMMARY_FORMATS, SUMMARY_MODES};
+use super::samples::GROUP_METHODS;
+use super::screen::MAX_REFERENCES;
+
+// CONFIGURATION FILE
+// rsfqc.toml - given with --config, or found in the working directory, or in $XDG_CONFIG_HOME/rsfqc/
+// (~/.config/rsfqc/ if not set). Every key is optional, see rsfqc.example.toml for all keys and defaults
+
+pub const CONFIG_FILE_NAME: &str = "rsfqc.toml";
+
+/// Output formats that can be listed in [output]
+/// text: terminal report in single mode, summary: summary table, see [summary] (always written in multi mode)
+/// json: <sample>.rsfqc.json for each file, html: <sample>.rsfqc.html for each file (plus rsFQC.index.html in multi mode)
+/// figures: every chart as SVG and/or PNG files, see [figures]
+/// fastqc: <sample>_fastqc/fastqc_data.txt and summary.txt like FastQC (zipped too with fastqc_zip)
+/// multiqc: rsfqc_*_mqc.tsv / .json MultiQC custom content for metrics FastQC does not have
+pub const OUTPUT_FORMATS: [&str; 7] = ["text", "summary", "json", "html", "figures", "fastqc", "multiqc"];
+/// Image formats that can be listed in [figures]
+pub const FIGURE_FORMATS: [&str; 2] = ["svg", "png"];
+
+#[derive(Debug,Clone,Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub records: usize, // number of records sampled from each file
+    pub threads: usize, // 0 = all CPUs
+    pub contaminants: Vec<String>, // [name=]reference.fa screened in every file, on top of PhiX and rRNA
+    pub modules: Modules,
+    pub plot: Plot,
+    pub long_reads: LongReads,
+    pub thresholds: Thresholds,
+    pub adapters: Vec<Adapter>,
+    pub output: Output,
+    pub figures: Figures,
+    pub summary: Summary,
+    pub groups: Groups,
+}
+
+/// Optional modules - quality and length are always computed
+#[derive(Debug,Clone,Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Modules {
+    pub duplication: bool,
+    pub complexity: bool,
+    pub contaminants: bool,
+    pub content: bool, // base composition and N content
+    pub adapters: bool,
+    pub long_reads: bool, // yield and length vs quality
+    pub illumina: bool, // lanes, tiles and indices
+    pub nanopore: bool,
+}
+
+#[derive(Debug,Clone,Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Plot {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug,Clone,Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LongReads {
+    pub end_profile_length: usize, // bases plotted from each end
+    pub min_reads_per_bin: usize, // bins covered by fewer reads are not plotted
+}
+
+#[derive(Debug,Clone,Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Adapter {
+    pub name: String,
+    pub sequence: String,
+}
+
+#[derive(Debug,Clone,Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Output {
+    pub formats: Vec<String>,
+    pub fastqc_zip: bool, // also write <sample>_fastqc.zip
+}
+
+/// Chart files written with the figures output format
+#[derive(Debug,Clone,Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Figures {
+    pub directory: String,
+    pub formats: Vec<String>,
+    pub width: u32, // SVG size in pixels, PNG size at 96 dpi
+    pub height: u32,
+    pub dpi: u32, // PNG resolution
+}
+
+/// Summary table of all files
+#[derive(Debug,Clone,Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Summary {
+    pub format: String, // tsv, csv, jsonl or markdown
+    pub prefix: String, // <prefix>.summary.<extension>, can include a directory
+    pub mode: String, // overwrite, append or error if the file exists
+}
+
+/// Lanes and replicates grouped into samples in multi mode
+#[derive(Debug,Clone,Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Groups {
+    pub by: String, // none, illumina, regex or sheet
+    pub regex: String, // on the file name without extension, named groups "sample" and "lane"
+    pub sheet: String, // CSV or TSV with file, sample and optional lane columns
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            records: 100000,
+            threads: 0,
+            contaminants: Vec::new(),
+            modules: Modules::default(),
+            plot: Plot::default(),
+            long_reads: LongReads::default(),
+            thresholds: Thresholds::default(),
+            adapters: ADAPTERS.iter().map(|(name, sequence)| Adapter {name: name.to_string(), sequence: sequence.to_string()}).collect(),
+            output: Output::default(),
+            figures: Figures::default(),
+            summary: Summary::default(),
+            groups: Groups::default(),
+        }
+    }
+}
+
+impl Default for Modules {
+    fn default() -> Self {
+        Self {duplication: true, complexity: true, contaminants: true, content: true, adapters: true, long_reads: true, illumina: true, nanopore: true}
+    }
+}
+
+impl Default for Plot {
+    fn default() -> Self {
+        Self {width: 140, height: 60}
+    }
+}
+
+impl Default for LongReads {
+    fn default() -> Self {
+        Self {end_profile_length: 5000, min_reads_per_bin: 20}
+    }
+}
+
+impl Default for Output {
+    fn default() -> Self {
+        Self {formats: vec!["text".to_string()], fastqc_zip: false}
+    }
+}
+
+impl Default for Figures {
+    fn default() -> Self {
+        Self {directory: "rsfqc_figures".to_string(), formats: vec!["svg".to_string(), "png".to_string()], width: 900, height: 400, dpi: 150}
+    }
+}
+
+impl Default for Summary {
+    fn default() -> Self {
+        Self {format: "tsv".to_string(), prefix: "rsFQC".to_string(), mode: "overwrite".to_string()}
+    }
+}
+
+impl Default for Groups {
+    fn default() -> Self {
+        Self {by: "none".to_string(), regex: String::new(), sheet: String::new()}
+    }
+}
+
+impl Config {
+    pub fn has_format(&self, format: &str) -> bool {
+        self.output.formats.iter().any(|x| x == format)
+    }
+}
+
+/// First rsfqc.toml found in the working directory then in the XDG config directory
+pub fn find_config() -> Option<PathBuf> {
+    let local = PathBuf::from(CONFIG_FILE_NAME);
+    if local.is_file() {
+        return Some(local)
+    }
+    let config_home = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(x) if !x.is_empty() => PathBuf::from(x),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    let global = config_home.join("rsfqc").join(CONFIG_FILE_NAME);
+    if global.is_file() {Some(global)} else {None}
+}
+
+/// Read and check a configuration file, the error explains what is wrong
+pub fn read_config(filepath: &PathBuf) -> Result<Config, String> {
+    let text = std::fs::read_to_string(filepath).map_err(|e| e.to_string())?;
+    let config: Config = toml::from_str(&text).map_err(|e| e.to_string())?;
+    if config.records == 0 {
+        return Err("records must be at least 1".to_string())
+    }
+    // A braille character is 4 dots high
+    if config.plot.width < 32 || config.plot.height < 4 {
+        return Err("plot width must be at least 32 and height at least 4".to_string())
+    }
+    if config.contaminants.len() > MAX_REFERENCES {
+        return Err(format!("at most {} contaminant references can be screened", MAX_REFERENCES))
+    }
+    if let Some(format) = config.output.formats.iter().find(|x| !OUTPUT_FORMATS.contains(&x.as_str())) {
+        return Err(format!("unknown output format {} (known: {})", format, OUTPUT_FORMATS.join(", ")))
+    }
+    if let Some(format) = config.figures.formats.iter().find(|x| !FIGURE_FORMATS.contains(&x.as_str())) {
+        return Err(format!("unknown figure format {} (known: {})", format, FIGURE_FORMATS.join(", ")))
+    }
+    if config.figures.width < 200 || config.figures.height < 150 || config.figures.dpi == 0 || config.figures.dpi > 1200 {
+        return Err("figure width must be at least 200, height at least 150 and dpi between 1 and 1200".to_string())
+    }
+    check_summary(&config.summary)?;
+    check_groups(&config.groups)?;
+    if let Some(adapter) = config.adapters.iter().find(|x| x.sequence.is_empty() || !x.sequence.bytes().all(|b| b"ACGTN".contains(&b.to_ascii_uppercase()))) {
+        return Err(format!("adapter {} must be a non empty ACGTN sequence", adapter.name))
+    }
+    Ok(config)
+}
+
+/// Check the summary settings (also used for the command line flags)
+pub fn check_summary(summary: &Summary) -> Result<(), String> {
+    if !SUMMARY_FORMATS.contains(&summary.format.as_str()) {
+        return Err(format!("unknown summary format {} (known: {})", summary.format, SUMMARY_FORMATS.join(", ")))
+    }
+    if !SUMMARY_MODES.contains(&summary.mode.as_str()) {
+        return Err(format!("unknown summary mode {} (known: {})", summary.mode, SUMMARY_MODES.join(", ")))
+    }
+    if summary.prefix.is_empty() {
+        return Err("summary prefix cannot be empty".to_string())
+    }
+    Ok(())
+}
+
+/// Check the grouping settings (also used for the command line flags)
+pub fn check_groups(groups: &Groups) -> Result<(), String> {
+    if !GROUP_METHODS.contains(&groups.by.as_str()) {
+        return Err(format!("unknown grouping {} (known: {})", groups.by, GROUP_METHODS.join(", ")))
+    }
+    if groups.by == "regex" && groups.regex.is_empty() {
+        return Err("grouping by regex needs a regex".to_string())
+    }
+    if groups.by == "sheet" && groups.sheet.is_empty() {
+        return Err("grouping by sheet needs a sheet".to_string())
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use std::sync::OnceLock;
+use serde::Serialize;
+use super::screen::{KmerIndex, ScreenReport, parse_fasta, add_reference, screen_reads};
+
+// BUILT-IN CONTAMINANTS
+// PhiX spike-in and rRNA k-mer sets embedded at compile time from data/contaminants
+// Every k-mer is kept (no sampling) as the references are small
+// References listed in contaminants = [...] in rsfqc.toml are screened as well
+
+const PHIX_FASTA: &[u8] = include_bytes!("../../data/contaminants/phix174.fa");
+const RRNA_FASTA: &[u8] = include_bytes!("../../data/contaminants/rrna.fa");
+const KMER_SIZE: usize = 21;
+
+#[derive(Debug,Clone,Default,Serialize)]
+pub struct ContaminantReport {
+    pub phix_percent: Option<f32>, // None if no PhiX sequence was embedded
+    pub rrna_percent: Option<f32>, // None if no rRNA sequence was embedded
+    pub custom: Vec<(String, f32)>, // references from the configuration file
+}
+
+/// Index of the PhiX (reference 0) and rRNA (reference 1) FASTA, and whether each has a sequence
+fn embedded_index(phix_fasta: &[u8], rrna_fasta: &[u8]) -> (KmerIndex, bool, bool) {
+    let mut index = KmerIndex::new(KMER_SIZE, 1);
+    let phix = parse_fasta(phix_fasta).unwrap_or_default();
+    let rrna = parse_fasta(rrna_fasta).unwrap_or_default();
+    let has_phix = phix.iter().any(|x| x.len() >= KMER_SIZE);
+    let has_rrna = rrna.iter().any(|x| x.len() >= KMER_SIZE);
+    add_reference(&mut index, "PhiX".to_string(), &phix);
+    add_reference(&mut index, "rRNA".to_string(), &rrna);
+    (index, has_phix, has_rrna)
+}
+
+/// Index of the embedded references, built once for all files
+fn builtin_index() -> &'static (KmerIndex, bool, bool) {
+    static INDEX: OnceLock<(KmerIndex, bool, bool)> = OnceLock::new();
+    INDEX.get_or_init(|| embedded_index(PHIX_FASTA, RRNA_FASTA))
+}
+
+/// % of reads hitting each reference of a screen
+fn hit_percents(report: &ScreenReport) -> Vec<(String, f32)> {
+    report.hits.iter()
+    .map(|hit| (hit.name.clone(), (hit.unique + hit.multiple) as f32 / report.reads.max(1) as f32 * 100.0))
+    .collect()
+}
+
+/// % of reads hitting PhiX, rRNA and the references from the configuration file
+pub fn contaminant_report(sequences: &[String], custom_index: Option<&KmerIndex>) -> ContaminantReport {
+    report_with_index(sequences, custom_index, builtin_index())
+}
+
+fn report_with_index(sequences: &[String], custom_index: Option<&KmerIndex>, builtin: &(KmerIndex, bool, bool)) -> ContaminantReport {
+    let custom = match custom_index {
+        Some(index) => hit_percents(&screen_reads(index, sequences)),
+        None => Vec::new(),
+    };
+    let (index, has_phix, has_rrna) = builtin;
+    if sequences.is_empty() || (!*has_phix && !*has_rrna) {
+        return ContaminantReport {custom, ..Default::default()}
+    }
+    let percents = hit_percents(&screen_reads(index, sequences));
+    ContaminantReport {
+        phix_percent: if *has_phix {Some(percents[0].1)} else {None},
+        rrna_percent: if *has_rrna {Some(percents[1].1)} else {None},
+        custom,
+    }
+}
+
+/// Percentage or n/a when the k-mer set is not available
+pub fn format_percent(percent: Option<f32>) -> String {
+    match percent {
+        Some(x) => format!("{:.2}", x),
+        None => "n/a".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Stand-in for the PhiX genome, the detection does not depend on the sequence itself
+    const PHIX_LIKE: &str = ">phix\nGATTTGGGGTTCAAAGCAGTATCGATCAAATAGTAAATCCATTTGTTCAACTCACAGTTTACGGTCAGCTTAGCCAGTCGATTGCCATGACTAGGCTAAC\n";
+
+    #[test]
+    fn phix_reads_are_counted() {
+        let builtin = embedded_index(PHIX_LIKE.as_bytes(), b"; no sequence\n");
+        let read = PHIX_LIKE.lines().nth(1).unwrap()[20..80].to_string();
+        let report = report_with_index(&[read.clone(), read], None, &builtin);
+        assert_eq!(report.phix_percent, Some(100.0));
+        assert_eq!(report.rrna_percent, None);
+        let report = report_with_index(&["ACGT".repeat(20)], None, &builtin);
+        assert_eq!(report.phix_percent, Some(0.0));
+    }
+
+    #[test]
+    fn missing_references_are_not_available() {
+        let report = report_with_index(&["ACGT".repeat(20)], None, &embedded_index(b"", b""));
+        assert_eq!((report.phix_percent, report.rrna_percent), (None, None));
+        assert_eq!(format_percent(report.phix_percent), "n/a");
+    }
+}
@@ -0,0 +1,61 @@
+use rayon::prelude::*;
+use serde::Serialize;
+
+// PER BASE SEQUENCE CONTENT
+// % of A, C, G, T and N at each of the first CONTENT_POSITIONS positions, like FastQC
+// Positions covered by fewer than min_reads reads are dropped (variable read lengths)
+
+const CONTENT_POSITIONS: usize = 1000;
+
+#[derive(Debug,Clone,Default,Serialize)]
+pub struct ContentReport {
+    pub positions: Vec<[f32; 5]>, // % A, C, G, T, N at each position
+    pub gc_percent: f32, // of all A, C, G, T
+    pub n_percent: f32, // of all bases
+    pub max_at_difference: f32, // largest |A - T| at a position
+    pub max_gc_difference: f32, // largest |G - C| at a position
+    pub max_n_percent: f32, // largest % N at a position
+}
+
+/// A C G T -> 0 1 2 3, anything else is an N
+fn base_index(base: u8) -> usize {
+    match base {b'A' | b'a' => 0, b'C' | b'c' => 1, b'G' | b'g' => 2, b'T' | b't' => 3, _ => 4}
+}
+
+/// Base composition of all reads
+pub fn content_report(sequences: &[String], min_reads: usize) -> ContentReport {
+    let (counts, totals) = sequences.par_iter()
+    .fold(|| (vec![[0u64; 5]; CONTENT_POSITIONS], [0u64; 5]), |(mut counts, mut totals), sequence| {
+        for (position, base) in sequence.bytes().enumerate() {
+            let index = base_index(base);
+            totals[index] += 1;
+            if position < CONTENT_POSITIONS {counts[position][index] += 1};
+        }
+        (counts, totals)
+    })
+    .reduce(|| (vec![[0u64; 5]; CONTENT_POSITIONS], [0u64; 5]), |(mut counts, mut totals), (other_counts, other_totals)| {
+        for (position, other) in counts.iter_mut().zip(other_counts) {
+            for i in 0..5 {position[i] += other[i]};
+        }
+        for i in 0..5 {totals[i] += other_totals[i]};
+        (counts, totals)
+    });
+
+    // Coverage only goes down with the position - stop at the first poorly covered one
+    let positions: Vec<[f32; 5]> = counts.iter()
+    .map(|x| (x, x.iter().sum::<u64>()))
+    .take_while(|(_, covered)| *covered >= min_reads.max(1) as u64)
+    .map(|(x, covered)| x.map(|count| count as f32 / covered as f32 * 100.0))
+    .collect();
+
+    let acgt: u64 = totals[..4].iter().sum();
+    let all: u64 = totals.iter().sum();
+    ContentReport {
+        gc_percent: if acgt > 0 {(totals[1] + totals[2]) as f32 / acgt as f32 * 100.0} else {0.0},
+        n_percent: if all > 0 {totals[4] as f32 / all as f32 * 100.0} else {0.0},
+        max_at_difference: positions.iter().map(|x| (x[0] - x[3]).abs()).fold(0.0, f32::max),
+        max_gc_difference: positions.iter().map(|x| (x[2] - x[1]).abs()).fold(0.0, f32::max),
+        max_n_percent: positions.iter().map(|x| x[4]).fold(0.0, f32::max),
+        positions,
+    }
+}
@@ -0,0 +1,78 @@
+// 2D DENSITY CHART
+// Read length (log10) on x, mean read quality on y, shaded by the number of reads in each cell
+
+const SHADES: [char; 5] = [' ', '░', '▒', '▓', '█'];
+
+#[derive(Debug,Clone)]
+pub struct DensityGrid {
+    pub counts: Vec<Vec<u64>>, // rows (top = highest quality) x columns
+    pub log_length_min: f32,
+    pub log_length_max: f32,
+    pub quality_min: f32,
+    pub quality_max: f32,
+}
+
+/// Bin reads by log10 length and mean quality
+pub fn length_quality_density(lengths: &[usize], qualities: &[f32], columns: usize, rows: usize) -> DensityGrid {
+    // At least one cell, tiny plots would have no row or column
+    let (columns, rows) = (columns.max(1), rows.max(1));
+    let log_lengths: Vec<f32> = lengths.iter().map(|x| (*x.max(&1) as f32).log10()).collect();
+    let log_length_min = log_lengths.iter().cloned().fold(f32::INFINITY, f32::min).floor();
+    let log_length_max = log_lengths.iter().cloned().fold(f32::NEG_INFINITY, f32::max).max(log_length_min + 1.0);
+    let quality_min = qualities.iter().cloned().fold(f32::INFINITY, f32::min).floor();
+    let quality_max = qualities.iter().cloned().fold(f32::NEG_INFINITY, f32::max).ceil().max(quality_min + 1.0);
+
+    let mut counts: Vec<Vec<u64>> = vec![vec![0; columns]; rows];
+    for (length, quality) in log_lengths.iter().zip(qualities) {
+        let column = ((length - log_length_min) / (log_length_max - log_length_min) * columns as f32) as usize;
+        let row = ((quality - quality_min) / (quality_max - quality_min) * rows as f32) as usize;
+        // Highest quality on the first row
+        counts[rows - 1 - row.min(rows - 1)][column.min(columns - 1)] += 1;
+    }
+
+    DensityGrid {counts, log_length_min, log_length_max, quality_min, quality_max}
+}
+
+/// Shade for a cell - log scaled so that sparse cells are still visible
+pub fn count_to_shade(count: u64, max_count: u64) -> char {
+    if count == 0 {
+        return SHADES[0]
+    }
+    if max_count <= 1 {
+        return SHADES[SHADES.len() - 1]
+    }
+    // 1 read = lightest shade, max_count = darkest
+    let level = 1 + ((count as f32).ln() / (max_count as f32).ln() * (SHADES.len() - 2) as f32).round() as usize;
+    SHADES[level.min(SHADES.len() - 1)]
+}
+
+/// Display the density grid with quality labels on the left and length labels at the bottom
+pub fn display_density(grid: &DensityGrid) {
+    let rows = grid.counts.len();
+    let columns = grid.counts.first().map(|x| x.len()).unwrap_or(0);
+    let max_count = grid.counts.iter().flatten().cloned().max().unwrap_or(0);
+
+    println!("{:>6} +{}+", "", "-".repeat(columns));
+    for (row, cells) in grid.counts.iter().enumerate() {
+        // Quality at the top of the row
+        let quality = grid.quality_max - row as f32 * (grid.quality_max - grid.quality_min) / rows as f32;
+        let label = if row % 2 == 0 {format!("Q{:.0}", quality)} else {String::new()};
+        let line: String = cells.iter().map(|x| count_to_shade(*x, max_count)).collect();
+        println!("{:>6} |{}|", label, line);
+    }
+    println!("{:>6} +{}+", "", "-".repeat(columns));
+
+    // One label per power of 10
+    let mut axis: Vec<char> = vec![' '; columns + 10];
+    let mut decade = grid.log_length_min.ceil();
+    while decade <= grid.log_length_max {
+        let column = ((decade - grid.log_length_min) / (grid.log_length_max - grid.log_length_min) * columns as f32) as usize;
+        let label = format!("{}", 10f32.powf(decade) as u64);
+        for (i, c) in label.chars().enumerate() {
+            if let Some(x) = axis.get_mut(column + i) {*x = c};
+        }
+        decade += 1.0;
+    }
+    println!("{:>6}  {}", "", axis.iter().collect::<String>().trim_end());
+    println!("Legend: '░' < '▒' < '▓' < '█' (log scaled, darkest = {} reads)", max_count);
+}
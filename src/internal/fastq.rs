@@ -1,4 +1,19 @@
 use crate::internal::func::{*};
+use crate::internal::illumina::{*};
+use crate::internal::barcodes::{*};
+use crate::internal::nanopore::{*};
+use crate::internal::density::{*};
+use crate::internal::platform::{*};
+use crate::internal::binning::{*};
+use crate::internal::complexity::{*};
+use crate::internal::screen::{*};
+use crate::internal::contaminants::{*};
+use crate::internal::content::{*};
+use crate::internal::adapters::{*};
+use crate::internal::verdict::{*};
+use crate::internal::outliers::Outlier;
+use crate::internal::config::{Modules, Adapter};
+use std::sync::Arc;
 use rayon::prelude::*;
 use itertools::Itertools;
 use textplots::{Chart, Plot, Shape, ColorPlot};
@@ -8,71 +23,200 @@ use rgb::RGB8;
 pub struct FqObject  {
     // General
     pub filepath: String,
+    pub sample: String, // file name, prefixed with its directories when another input has the same one
     pub number_of_records_used: usize,
+    pub first_n_headers: Vec<String>,
     pub first_n_sequences: Vec<String>,
     pub first_n_quality: Vec<Vec<u32>>,
     pub gzipped: bool, // default is false before being processed
+    pub file_bytes: u64, // size on disk - the only yield measure, reads are sampled
     pub processed: bool, // default is false before being processed
     pub isFastq: bool, // default is false before being processed
     pub isFile: bool,  // default is false before being processed
     pub isReadable: bool,  // default is false before being processed
 
     // Type of sequencing
-    pub isLongReads: bool,
+    pub platform: Platform,
+    pub platform_override: Option<Platform>, // from the command line
 
 
     // plotting
     pub plot_width: u32,
     pub plot_height: u32,
+    // Long reads quality profiles
+    pub end_profile_length: usize, // bases plotted from each end
+    pub min_reads_per_bin: usize, // bins covered by fewer reads are not plotted
 
-    // Quality
-    pub qual_min: u32,
-    pub qual_mean: u32,
-    pub qual_median: u32,
-    pub qual_max: u32,
+    // Quality - per read mean quality (error probability space)
+    pub read_mean_qualities: Vec<f32>,
+    pub qual_min: f32,
+    pub qual_q1: f32,
+    pub qual_mean: f32,
+    pub qual_median: f32,
+    pub qual_q3: f32,
+    pub qual_max: f32,
+    // Quality - base level
+    pub quality_histogram: Vec<u64>, // index is the phred score
+    pub percent_q20: f32,
+    pub percent_q30: f32,
+    pub percent_q40: f32,
+    pub quality_alphabet: Vec<(u32, u64)>, // distinct phred scores, bases
+    pub quality_binning: Option<String>, // binning scheme, None if not binned
+    pub quality_encoding: &'static str, // Phred+33, or Phred+64 converted to Phred+33
     // Length
+    pub length_histogram: Vec<u64>, // index is the read length
     pub len_min: u32,
-    pub len_mean: u32,
-    pub len_median: u32,
+    pub len_q1: f32,
+    pub len_mean: f32,
+    pub len_median: f32,
+    pub len_q3: f32,
     pub len_max: u32,
+    // Yield
+    pub total_bases: u64,
+    pub n10: u32,
+    pub n50: u32,
+    pub n90: u32,
+    pub l50: u64,
+    pub reads_above_thresholds: Vec<(u32, u64, u64)>, // length threshold, reads, bases
+    // Quality at each position - position (1 based), mean quality, reads covering it
+    pub position_qualities: Vec<(usize, f32, usize)>,
+    // Long reads - binned quality from each end - distance to the end, mean quality, reads
+    pub end_profile_bin_size: usize,
+    pub five_prime_profile: Vec<(usize, f32, usize)>,
+    pub three_prime_profile: Vec<(usize, f32, usize)>,
     // Duplication
-    pub duplication_levels: f32
+    pub duplication_levels: f32,
+    pub duplication_histogram: Vec<(usize, usize)>, // times seen (10 = 10 or more), sequences
+    // Complexity
+    pub complexity: ComplexityReport,
+    // PhiX and rRNA
+    pub contaminants: ContaminantReport,
+    // Base composition and adapters
+    pub content: ContentReport,
+    pub adapters: AdapterReport,
+    // From rsfqc.toml
+    pub modules: Modules,
+    pub adapter_list: Vec<Adapter>,
+    pub custom_contaminants: Option<Arc<KmerIndex>>,
+    // Verdicts
+    pub thresholds: Thresholds,
+    pub verdicts: Verdicts,
+    // Contamination screen (screen subcommand only)
+    pub screen_report: Option<ScreenReport>,
+    // Cross-sample outliers (multi mode) - None when not compared
+    pub outliers: Option<Vec<Outlier>>,
+    // Sample and lane when files are grouped (multi mode)
+    pub sample_group: Option<String>,
+    pub lane: Option<String>,
+    // Illumina headers - None when the read name is not Casava 1.8+
+    pub illumina_headers: Vec<Option<IlluminaHeader>>,
+    pub exclude_filtered: bool, // drop chastity failed reads (Y) from all metrics
+    pub reads_filtered: usize, // Y
+    pub reads_passing_filter: usize, // N
+    pub control_counts: Vec<(u32, usize)>, // control number, reads
+    pub tile_bin_size: usize,
+    pub tile_deviations: Vec<TileDeviation>,
+    pub lane_deviations: Vec<LaneDeviation>,
+    // Indices
+    pub expected_indices: Vec<ExpectedIndex>, // from the sample sheet
+    pub index_report: IndexReport,
+    // Nanopore read names
+    pub nanopore_report: NanoporeReport,
 }
 
 impl FqObject{
     // Create new unprocessed 
     pub fn new(filepath: String, number_of_records_to_get: usize) -> Self {
         Self {
-            filepath: filepath,
+            sample: sample_name(&filepath),
+            filepath,
             isFile: false,
             number_of_records_used: number_of_records_to_get,
+            first_n_headers: Vec::new(),
             first_n_sequences: Vec::new(),
             first_n_quality: Vec::new(),
             gzipped: false, 
+            file_bytes: 0,
             processed: false,
             isFastq: false,
             isReadable:false, 
 
             // Type of sequencing
-            isLongReads: false,
+            platform: Platform::Unknown,
+            platform_override: None,
 
             // plotting
             plot_width: 140,
             plot_height: 60,
+            end_profile_length: 5000,
+            min_reads_per_bin: 20,
             
             // Yet unknown
             // Quality
-            qual_min: 0,
-            qual_mean: 0,
-            qual_median: 0,
-            qual_max: 0,
+            read_mean_qualities: Vec::new(),
+            qual_min: 0.0,
+            qual_q1: 0.0,
+            qual_mean: 0.0,
+            qual_median: 0.0,
+            qual_q3: 0.0,
+            qual_max: 0.0,
+            quality_histogram: Vec::new(),
+            percent_q20: 0.0,
+            percent_q30: 0.0,
+            percent_q40: 0.0,
+            quality_alphabet: Vec::new(),
+            quality_binning: None,
+            quality_encoding: PHRED33,
             // Length
+            length_histogram: Vec::new(),
             len_min: 0,
-            len_mean: 0,
-            len_median: 0,
+            len_q1: 0.0,
+            len_mean: 0.0,
+            len_median: 0.0,
+            len_q3: 0.0,
             len_max: 0,
+            // Yield
+            total_bases: 0,
+            n10: 0,
+            n50: 0,
+            n90: 0,
+            l50: 0,
+            reads_above_thresholds: Vec::new(),
             // Duplication
-            duplication_levels: 0.0
+            position_qualities: Vec::new(),
+            end_profile_bin_size: 1,
+            five_prime_profile: Vec::new(),
+            three_prime_profile: Vec::new(),
+            duplication_levels: 0.0,
+            duplication_histogram: Vec::new(),
+            // Complexity
+            complexity: ComplexityReport::default(),
+            contaminants: ContaminantReport::default(),
+            content: ContentReport::default(),
+            adapters: AdapterReport::default(),
+            modules: Modules::default(),
+            adapter_list: Vec::new(),
+            custom_contaminants: None,
+            thresholds: Thresholds::default(),
+            verdicts: Verdicts::default(),
+            screen_report: None,
+            outliers: None,
+            sample_group: None,
+            lane: None,
+            // Illumina
+            illumina_headers: Vec::new(),
+            exclude_filtered: false,
+            reads_filtered: 0,
+            reads_passing_filter: 0,
+            control_counts: Vec::new(),
+            tile_bin_size: 1,
+            tile_deviations: Vec::new(),
+            lane_deviations: Vec::new(),
+            // Indices
+            expected_indices: Vec::new(),
+            index_report: IndexReport::default(),
+            // Nanopore
+            nanopore_report: NanoporeReport::default(),
         }
     }
 
@@ -85,6 +229,7 @@ impl FqObject{
         if self.isFile{
             // 1 - Get encoding and set flag - GZIP OR OTHER
             self.gzipped  = get_encoding(&self.filepath);
+            self.file_bytes = std::fs::metadata(&self.filepath).map(|x| x.len()).unwrap_or(0);
 
             // 2 - Can get read as UTF8
             self.isReadable = is_readable(&self.filepath, self.gzipped);
@@ -92,182 +237,604 @@ impl FqObject{
             if self.isFile & self.isReadable {
 
                 //3  - Is it a proper FastQ file
-                self.isFastq = is_fastq_file(&self);
+                self.isFastq = is_fastq_file(self);
             }
         }
     } // used to filter our fastq holder and remove bad files
 
     pub fn process_single(& mut self){
         // 3 populate the main fields
-        self.first_n_sequences = get_first_n_sequence_lines_of_fastq_file(&self);
-        self.first_n_quality = get_first_n_quality_lines_of_fastq_file(&self);
+        self.first_n_headers = get_first_n_header_lines_of_fastq_file(self);
+        self.first_n_sequences = get_first_n_sequence_lines_of_fastq_file(self);
+        self.first_n_quality = get_first_n_quality_lines_of_fastq_file(self);
+        self.encoding_detection();
+        self.header_calculation();
         
         self.length_quartiles();
-        if self.len_max >= 1000 {self.isLongReads = true}; // set the longreads tag if long reads are detected
+        self.quality_quartiles();
+        self.platform_detection();
+        self.position_quality_calculation();
         header(self);
         sep();
-        if self.isLongReads {println!("Long Reads Mode");}else{println!("Short Read Mode")}
-        self.quality_quartiles();
-        self.duplication_calculation();
+        match self.platform_override {
+            Some(_) => println!("Platform: {} (set from the command line)", self.platform.name()),
+            None => println!("Platform: {} (detected)", self.platform.name()),
+        }
+        if self.quality_encoding == PHRED64 {println!("Quality encoding: {} (converted to {})", PHRED64, PHRED33)};
+        if self.platform.is_long_reads() {println!("Long Reads Mode");}else{println!("Short Read Mode")}
+        self.module_calculations();
         sep();
-        println!("DUPLICATION");
+        println!("STATUS");
         sep();
-        println!("Duplication level: {}%", 100.-self.duplication_levels);
-        self.duplication_chart();
+        for (module, verdict) in self.verdicts.list() {
+            match verdict {
+                Some(verdict) => println!("[{}] {}", verdict.colored(), module),
+                None => println!("[NA] {} (disabled)", module),
+            }
+        }
+        if self.modules.duplication {
+            sep();
+            println!("DUPLICATION");
+            sep();
+            println!("Duplication level: {}%", 100.-self.duplication_levels);
+            self.duplication_chart();
+        }
+        if self.modules.complexity {
+            sep();
+            println!("COMPLEXITY");
+            sep();
+            self.complexity_charts();
+        }
+        if self.modules.contaminants {
+            sep();
+            println!("CONTAMINANTS");
+            sep();
+            self.contaminant_tables();
+        }
+        if self.modules.content {
+            sep();
+            println!("CONTENT");
+            sep();
+            self.content_charts();
+        }
+        if self.modules.adapters {
+            sep();
+            println!("ADAPTERS");
+            sep();
+            self.adapter_tables();
+        }
         sep();
         println!("QUALITY");
         sep();
         println!("Mean Read Quality Distribution");
-        println!("Min Q\tMed Q\tAvg Q\tMax Q");
-        println!("{}\t{}\t{}\t{}", self.qual_min, self.qual_median, self.qual_mean, self.qual_max);
+        println!("Min Q\tQ1\tMed Q\tAvg Q\tQ3\tMax Q");
+        println!("{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.2}", self.qual_min, self.qual_q1, self.qual_median, self.qual_mean, self.qual_q3, self.qual_max);
+        println!("Base Quality");
+        println!(">= Q20\t>= Q30\t>= Q40");
+        println!("{:.2}%\t{:.2}%\t{:.2}%", self.percent_q20, self.percent_q30, self.percent_q40);
+        self.quality_alphabet_table();
         self.quality_charts();
         sep();
         println!("LENGTH");
         sep();
         println!("Read Length Distribution");
-        println!("Min L\tMed L\tAvg L\tMax L");
-        println!("{}\t{}\t{}\t{}", self.len_min, self.len_median, self.len_mean, self.len_max);
+        println!("Min L\tQ1\tMed L\tAvg L\tQ3\tMax L");
+        println!("{}\t{}\t{}\t{:.2}\t{}\t{}", self.len_min, self.len_q1, self.len_median, self.len_mean, self.len_q3, self.len_max);
         self.length_charts();
+        if self.platform.is_long_reads() && self.modules.long_reads {
+            sep();
+            println!("YIELD");
+            sep();
+            self.yield_tables();
+            self.yield_charts();
+            sep();
+            println!("LENGTH VS QUALITY");
+            sep();
+            self.density_chart();
+        }
+        if self.platform.has_illumina_headers() && self.modules.illumina {self.illumina_calculation()};
+        if !self.tile_deviations.is_empty() {
+            sep();
+            println!("ILLUMINA LANES AND TILES");
+            sep();
+            self.tile_charts();
+        }
+        if self.index_report.reads_with_index > 0 {
+            sep();
+            println!("INDICES");
+            sep();
+            self.index_tables();
+        }
+        if self.platform == Platform::Nanopore && self.modules.nanopore {self.nanopore_calculation()};
+        if self.nanopore_report.reads_with_header > 0 {
+            sep();
+            println!("NANOPORE RUN");
+            sep();
+            self.nanopore_charts();
+        }
+        self.processed = true;
     }
 
     pub fn process_multi(& mut self){
         // 3 populate the main fields
-        self.first_n_sequences = get_first_n_sequence_lines_of_fastq_file(&self);
-        self.first_n_quality = get_first_n_quality_lines_of_fastq_file(&self);
+        self.first_n_headers = get_first_n_header_lines_of_fastq_file(self);
+        self.first_n_sequences = get_first_n_sequence_lines_of_fastq_file(self);
+        self.first_n_quality = get_first_n_quality_lines_of_fastq_file(self);
+        self.encoding_detection();
+        self.header_calculation();
         self.length_quartiles();
-        if self.len_max >= 1000 {self.isLongReads = true}; // set the longreads tag if long reads are detected
         self.quality_quartiles();
-        self.duplication_calculation();
+        self.platform_detection();
+        self.position_quality_calculation();
+        self.module_calculations();
+        if self.platform.has_illumina_headers() && self.modules.illumina {self.illumina_calculation()};
+        if self.platform == Platform::Nanopore && self.modules.nanopore {self.nanopore_calculation()};
+        self.processed = true;
     }
 
-    pub fn length_quartiles(& mut self){
+    // Contamination screen against the k-mers of local references
+    pub fn process_screen(& mut self, index: &KmerIndex){
+        self.first_n_sequences = get_first_n_sequence_lines_of_fastq_file(self);
+        let report = screen_reads(index, &self.first_n_sequences);
+        sep();
+        println!("SCREEN");
+        println!("{}", self.filepath);
+        sep();
+        let percent = |x: usize| x as f32 / report.reads as f32 * 100.0;
+        println!("Reads screened: {}", pretty_print_int(&report.reads));
+        println!("Reference\t% Unique\t% Multiple\t% Any");
+        for hit in &report.hits {
+            println!("{}\t{:.2}\t\t{:.2}\t\t{:.2}", hit.name, percent(hit.unique), percent(hit.multiple), percent(hit.unique + hit.multiple));
+        }
+        println!("No hits\t\t{:.2}", percent(report.no_hits));
+        self.screen_report = Some(report);
+        self.processed = true;
+    }
 
-        let size_of_filtered_lines: Vec<usize> = self.first_n_sequences
-        .par_iter()
-        .map(|x: &String| x.len())
-        .collect();
+    pub fn length_quartiles(& mut self){
+        // No complete record - keep the defaults
+        if self.first_n_sequences.is_empty() {
+            return
+        }
 
-        let maximum = size_of_filtered_lines.par_iter().max().unwrap().to_owned();
-        let minimum = size_of_filtered_lines.par_iter().min().unwrap().to_owned();
+        // Histogram of read lengths - index is the length
+        let maximum: usize = self.first_n_sequences.par_iter().map(|x: &String| x.len()).max().unwrap();
+        let mut histogram: Vec<u64> = vec![0; maximum + 1];
+        for sequence in &self.first_n_sequences {
+            histogram[sequence.len()] += 1;
+        }
 
-        let total : usize = size_of_filtered_lines.par_iter().sum();
-        let mean = total / size_of_filtered_lines.len();
-        let median = size_of_filtered_lines.get(size_of_filtered_lines.len()/2).unwrap().to_owned();
+        let minimum = histogram.iter().position(|x| *x > 0).unwrap();
+        let total_reads: u64 = histogram.iter().sum();
+        let total_bases: u64 = histogram.iter().enumerate().map(|(length, count)| length as u64 * count).sum();
 
         self.len_min = minimum as u32;
         self.len_max = maximum as u32;
-        self.len_median = median as u32;
-        self.len_mean = mean as u32;
+        self.len_q1 = histogram_quantile(&histogram, 0.25) as f32;
+        self.len_median = histogram_quantile(&histogram, 0.5) as f32;
+        self.len_q3 = histogram_quantile(&histogram, 0.75) as f32;
+        self.len_mean = (total_bases as f64 / total_reads as f64) as f32;
+        self.total_bases = total_bases;
+
+        // Read length N50 and friends
+        let (n10, _) = histogram_nx(&histogram, 0.1);
+        let (n50, l50) = histogram_nx(&histogram, 0.5);
+        let (n90, _) = histogram_nx(&histogram, 0.9);
+        self.n10 = n10 as u32;
+        self.n50 = n50 as u32;
+        self.n90 = n90 as u32;
+        self.l50 = l50;
+        self.reads_above_thresholds = [1_000, 5_000, 10_000, 50_000, 100_000]
+        .iter()
+        .map(|threshold| {
+            let above = histogram.iter().enumerate().skip(*threshold);
+            let reads: u64 = above.clone().map(|(_, count)| count).sum();
+            let bases: u64 = above.map(|(length, count)| length as u64 * count).sum();
+            (*threshold as u32, reads, bases)
+        })
+        .collect();
+        self.length_histogram = histogram;
     }
 
     // QUality Distributions
     pub fn quality_quartiles(& mut self){
+        // No complete record - keep the defaults
+        if self.first_n_quality.is_empty() {
+            return
+        }
 
-        let mean_quality_of_filtered_lines: Vec<u32> = self.first_n_quality
+        // Base level histogram of phred scores (0 - 93)
+        let histogram: Vec<u64> = self.first_n_quality
         .par_iter()
-        .map(|x|  x.par_iter().sum::<u32>()/ (x.len() as u32))
+        .fold(|| vec![0u64; 94], |mut acc, x| {
+            for q in x {acc[(*q as usize).min(93)] += 1};
+            acc
+        })
+        .reduce(|| vec![0u64; 94], |mut a, b| {
+            for (i, count) in b.iter().enumerate() {a[i] += count};
+            a
+        });
+
+        self.percent_q20 = histogram_percent_above(&histogram, 20);
+        self.percent_q30 = histogram_percent_above(&histogram, 30);
+        self.percent_q40 = histogram_percent_above(&histogram, 40);
+        self.quality_alphabet = quality_alphabet(&histogram);
+        self.quality_binning = detect_binning(&self.quality_alphabet);
+        self.quality_histogram = histogram;
+
+        // Mean quality of each read in error probability space
+        let mean_quality_of_filtered_lines: Vec<f32> = self.first_n_quality
+        .par_iter()
+        .map(|x| mean_read_quality(x))
         .collect();
 
-        let maximum = mean_quality_of_filtered_lines.par_iter().max().unwrap().to_owned();
-        let minimum = mean_quality_of_filtered_lines.par_iter().min().unwrap().to_owned();
+        let mut sorted_qualities = mean_quality_of_filtered_lines.clone();
+        sorted_qualities.par_sort_unstable_by(|a, b| a.total_cmp(b));
+
+        let total: f64 = sorted_qualities.iter().map(|x| *x as f64).sum();
+
+        self.qual_min = *sorted_qualities.first().unwrap();
+        self.qual_max = *sorted_qualities.last().unwrap();
+        self.qual_q1 = sorted_quantile(&sorted_qualities, 0.25);
+        self.qual_median = sorted_quantile(&sorted_qualities, 0.5);
+        self.qual_q3 = sorted_quantile(&sorted_qualities, 0.75);
+        self.qual_mean = (total / sorted_qualities.len() as f64) as f32;
+        self.read_mean_qualities = mean_quality_of_filtered_lines;
+    }
+
+    // Mean quality at each position (every 10 bases for long reads) and the profiles from each end
+    pub fn position_quality_calculation(& mut self) {
+        let step = if self.platform.is_long_reads() {10} else {1};
 
-        let total : u32 = mean_quality_of_filtered_lines.par_iter().sum();
-        let mean = total / (mean_quality_of_filtered_lines.len() as u32);
-        let median = mean_quality_of_filtered_lines.get(mean_quality_of_filtered_lines.len()/2).unwrap().to_owned();
+        self.position_qualities = position_qualities(&self.first_n_quality, step);
 
-        self.qual_min = minimum as u32;
-        self.qual_max = maximum as u32;
-        self.qual_median = median as u32;
-        self.qual_mean = mean as u32;
+        if self.platform.is_long_reads() {
+            let window = self.end_profile_length.min(self.len_max as usize);
+            self.end_profile_bin_size = position_bin_size(window, self.plot_width as usize);
+            self.five_prime_profile = quality_profile_from_end(&self.first_n_quality, window, self.end_profile_bin_size, false);
+            self.three_prime_profile = quality_profile_from_end(&self.first_n_quality, window, self.end_profile_bin_size, true);
+        }
+    }
+
+    // Old Illumina files - bring the qualities back to Phred+33 before any statistics
+    pub fn encoding_detection(& mut self) {
+        self.quality_encoding = detect_quality_encoding(&self.first_n_quality);
+        if self.quality_encoding == PHRED64 {
+            for q in self.first_n_quality.iter_mut().flatten() {
+                *q = q.saturating_sub(PHRED64_SHIFT);
+            }
+        }
+    }
+
+    // Guess the sequencing platform unless it was given on the command line
+    pub fn platform_detection(& mut self) {
+        self.platform = match self.platform_override {
+            Some(platform) => platform,
+            None => detect_platform(&self.first_n_headers, &self.length_histogram, &self.quality_histogram),
+        };
+    }
+
+    // Parse Illumina read names, count filter flags and controls
+    // and drop filtered reads if asked
+    pub fn header_calculation(& mut self) {
+        self.illumina_headers = self.first_n_headers
+        .par_iter()
+        .map(|x| parse_illumina_header(x))
+        .collect();
+
+        self.reads_filtered = self.illumina_headers.iter().flatten().filter(|x| x.is_filtered == Some(true)).count();
+        self.reads_passing_filter = self.illumina_headers.iter().flatten().filter(|x| x.is_filtered == Some(false)).count();
+        self.control_counts = self.illumina_headers.iter()
+        .flatten()
+        .filter_map(|x| x.control)
+        .counts()
+        .into_iter()
+        .sorted()
+        .collect();
+
+        if self.exclude_filtered && self.reads_filtered > 0 {
+            if self.reads_passing_filter == 0 {
+                println!("All reads are filtered (Y) in {}, keeping them", self.filepath);
+                return
+            }
+            let keep: Vec<bool> = self.illumina_headers.iter()
+            .map(|x| x.as_ref().and_then(|x| x.is_filtered) != Some(true))
+            .collect();
+            let mut keep_iter = keep.iter();
+            self.illumina_headers.retain(|_| *keep_iter.next().unwrap());
+            let mut keep_iter = keep.iter();
+            self.first_n_headers.retain(|_| *keep_iter.next().unwrap());
+            let mut keep_iter = keep.iter();
+            self.first_n_sequences.retain(|_| *keep_iter.next().unwrap());
+            let mut keep_iter = keep.iter();
+            self.first_n_quality.retain(|_| *keep_iter.next().unwrap());
+        }
+    }
+
+    // Get per lane / per tile quality deviations from the Illumina read names
+    pub fn illumina_calculation(& mut self) {
+        if self.illumina_headers.iter().all(|x| x.is_none()) {
+            return
+        }
+
+        // One column per character of the heatmap
+        let number_of_bins = (self.plot_width as usize / 2).min(self.len_max as usize).max(1);
+        self.tile_bin_size = position_bin_size(self.len_max as usize, number_of_bins);
+        let number_of_bins = (self.len_max as usize).div_ceil(self.tile_bin_size);
+
+        self.tile_deviations = tile_deviations(&self.illumina_headers, &self.first_n_quality, self.tile_bin_size, number_of_bins);
+        self.lane_deviations = lane_deviations(&self.illumina_headers, &self.first_n_quality, &self.tile_deviations);
+        self.index_report = index_report(&self.illumina_headers, &self.expected_indices);
+    }
+
+    // Display lane and tile tables and the tile heatmap
+    pub fn tile_charts(& mut self) {
+        let mut runs: Vec<String> = self.illumina_headers
+        .iter()
+        .flatten()
+        .map(|x| format!("{}\t{}\t{}", x.instrument, x.run, x.flowcell))
+        .collect();
+        runs.sort();
+        runs.dedup();
+        println!("Instrument\tRun\tFlowcell");
+        for run in runs {println!("{}", run)};
+        println!();
+        println!("Lane\tTiles\tReads\tMean Q\tDeviation");
+        for lane in &self.lane_deviations {
+            println!("{}\t{}\t{}\t{:.2}\t{:+.2}", lane.lane, lane.tiles, pretty_print_int(&lane.reads), lane.mean_quality, lane.deviation);
+        }
+        println!();
+        println!("Lane\tTile\tReads\tMean Dev\tWorst Dev");
+        for tile in &self.tile_deviations {
+            println!("{}\t{}\t{}\t{:+.2}\t\t{:+.2}", tile.lane, tile.tile, pretty_print_int(&tile.reads), tile.mean_deviation, tile.worst_deviation);
+        }
+        println!("\nPer tile quality - deviation from the mean of all tiles at each position");
+        tile_heatmap(&self.tile_deviations, self.tile_bin_size);
+    }
+
+    // Display index composition and sample sheet matching
+    pub fn index_tables(& mut self) {
+        let report = &self.index_report;
+        let percent = |x: usize| x as f32 / report.reads_with_index as f32 * 100.0;
+        println!("Reads with an index: {}", pretty_print_int(&report.reads_with_index));
+        println!("Reads with N in the index: {} ({:.2}%)", pretty_print_int(&report.reads_with_n), percent(report.reads_with_n));
+
+        if report.is_dual {println!("\nMost frequent i7+i5 combinations")} else {println!("\nMost frequent indices")};
+        println!("Index\tReads\t%");
+        for (index, count) in &report.top_indices {
+            println!("{}\t{}\t{:.2}", index, pretty_print_int(count), percent(*count));
+        }
+        if report.is_dual {
+            for (name, top) in [("i7", &report.top_i7), ("i5", &report.top_i5)] {
+                println!("\nMost frequent {}", name);
+                println!("Index\tReads\t%");
+                for (index, count) in top {
+                    println!("{}\t{}\t{:.2}", index, pretty_print_int(count), percent(*count));
+                }
+            }
+        }
+
+        if !self.expected_indices.is_empty() {
+            println!("\nSample sheet ({} samples)", report.per_sample.len());
+            if report.i5_reverse_complemented {println!("index2 matched as reverse complement")};
+            println!("Perfect match\t1 mismatch\tHopped\tUndetermined");
+            println!("{:.2}%\t\t{:.2}%\t\t{:.2}%\t{:.2}%", percent(report.perfect_match), percent(report.one_mismatch), percent(report.hopped), percent(report.undetermined));
+            println!("\nSample\tReads\t%");
+            for (sample, count) in &report.per_sample {
+                println!("{}\t{}\t{:.2}", sample, pretty_print_int(count), percent(*count));
+            }
+        }
+    }
+
+    // Parse nanopore read names (runid=, ch=, start_time=...)
+    pub fn nanopore_calculation(& mut self) {
+        let lengths: Vec<usize> = self.first_n_sequences.par_iter().map(|x| x.len()).collect();
+        self.nanopore_report = nanopore_report(&self.first_n_headers, &lengths);
+    }
+
+    // Display run ids, basecall models, channels and yield over time
+    pub fn nanopore_charts(& mut self) {
+        let report = &self.nanopore_report;
+        println!("Reads with nanopore fields: {}", pretty_print_int(&report.reads_with_header));
+        for (name, values) in [("Run ids", &report.run_ids), ("Flow cells", &report.flow_cell_ids), ("Basecall models", &report.basecall_models)] {
+            if values.is_empty() {continue}
+            println!("\n{}\tReads", name);
+            for (value, count) in values {
+                println!("{}\t{}", value, pretty_print_int(count));
+            }
+        }
+        if report.basecall_models.len() > 1 {println!("\nWARNING: reads from {} different basecall models", report.basecall_models.len())};
+
+        if !report.channels.is_empty() {
+            let mut reads_per_channel: Vec<f32> = report.channels.iter().map(|x| x.1 as f32).collect();
+            reads_per_channel.sort_by(|a, b| a.total_cmp(b));
+            let total_bases: u64 = report.channels.iter().map(|x| x.2).sum();
+            println!("\nActive channels: {}", pretty_print_int(&report.active_channels));
+            println!("Reads per channel (min / median / max): {} / {} / {}",
+                reads_per_channel[0], sorted_quantile(&reads_per_channel, 0.5), reads_per_channel[reads_per_channel.len() - 1]);
+            println!("Bases per channel (mean): {}", pretty_print_int(&((total_bases / report.active_channels as u64) as usize)));
+
+            let max_channel = report.channels.last().unwrap().0;
+            let points_for_channels: Vec<(f32, f32)> = report.channels.iter().map(|(channel, reads, _)| (*channel as f32, *reads as f32)).collect();
+            println!("\ny = Number of reads per channel");
+            Chart::new(self.plot_width, self.plot_height, 0.0, max_channel as f32 + 1.0)
+            .lineplot(&Shape::Bars(&points_for_channels))
+            .display();
+        }
+
+        if report.yield_over_time.len() > 1 {
+            println!("\nRun duration covered by the sampled reads: {:.2} hours", report.run_hours);
+            let points_for_yield: Vec<(f32, f32)> = report.yield_over_time.iter().map(|(hours, bases)| (*hours, *bases as f32 / 1e6)).collect();
+            println!("\ny = Cumulative yield (Mb) over time (hours)");
+            Chart::new(self.plot_width, self.plot_height, 0.0, points_for_yield.last().unwrap().0)
+            .lineplot(&Shape::Lines(&points_for_yield))
+            .display();
+        }
+    }
+
+    // DUST score and entropy of each read
+    pub fn complexity_calculation(& mut self) {
+        self.complexity = complexity_report(&self.first_n_sequences);
+    }
+
+    // Optional modules enabled in rsfqc.toml, then the verdicts
+    pub fn module_calculations(& mut self) {
+        if self.modules.duplication {self.duplication_calculation()};
+        if self.modules.complexity {self.complexity_calculation()};
+        if self.modules.contaminants {self.contaminant_calculation()};
+        if self.modules.content {self.content_calculation()};
+        if self.modules.adapters {self.adapter_calculation()};
+        self.verdict_calculation();
+    }
+
+    pub fn contaminant_calculation(& mut self) {
+        self.contaminants = contaminant_report(&self.first_n_sequences, self.custom_contaminants.as_deref());
+    }
+
+    // Display % of reads from PhiX, rRNA and the configured references
+    pub fn contaminant_tables(& mut self) {
+        for (name, percent) in [("PhiX spike-in", self.contaminants.phix_percent), ("rRNA", self.contaminants.rrna_percent)] {
+            match percent {
+                Some(x) => println!("{}: {:.2}% of reads", name, x),
+                None => println!("{}: n/a (no sequence embedded in data/contaminants)", name),
+            }
+        }
+        for (name, percent) in &self.contaminants.custom {
+            println!("{}: {:.2}% of reads", name, percent);
+        }
+    }
+
+    pub fn content_calculation(& mut self) {
+        let min_reads = if self.platform.has_variable_length() {self.min_reads_per_bin} else {1};
+        self.content = content_report(&self.first_n_sequences, min_reads);
+    }
+
+    pub fn adapter_calculation(& mut self) {
+        self.adapters = adapter_report(&self.first_n_sequences, &self.adapter_list);
+    }
+
+    // PASS / WARN / FAIL for each module - needs all the calculations above
+    pub fn verdict_calculation(& mut self) {
+        let t = &self.thresholds;
+        let length = if self.len_min < t.length_fail_below {Verdict::Fail}
+            else if t.length_warn_variable && !self.platform.has_variable_length() && self.len_min != self.len_max {Verdict::Warn}
+            else {Verdict::Pass};
+        let (quality_warn, quality_fail) = t.quality(self.platform);
+        let modules = &self.modules;
+        self.verdicts = Verdicts {
+            quality: Some(verdict_below(self.qual_median, quality_warn, quality_fail)),
+            length: Some(length),
+            duplication: modules.duplication.then(|| verdict_above(100.0 - self.duplication_levels, t.duplication_warn, t.duplication_fail)),
+            content: modules.content.then(|| verdict_above(self.content.max_at_difference.max(self.content.max_gc_difference), t.content_warn, t.content_fail)),
+            adapters: modules.adapters.then(|| verdict_above(self.adapters.max_percent, t.adapters_warn, t.adapters_fail)),
+            n: modules.content.then(|| verdict_above(self.content.max_n_percent, t.n_warn, t.n_fail)),
+        };
+    }
+
+    // Display base composition along the reads
+    pub fn content_charts(& mut self) {
+        let report = &self.content;
+        println!("GC content: {:.2}%\tN content: {:.2}%", report.gc_percent, report.n_percent);
+        println!("Largest difference at a position: A/T {:.2}%\tG/C {:.2}%\tLargest N at a position: {:.2}%", report.max_at_difference, report.max_gc_difference, report.max_n_percent);
+        if report.positions.len() < 2 {
+            return
+        }
+        let colors = [RGB8 {r:0, g:200, b:0}, RGB8 {r:0, g:100, b:255}, RGB8 {r:255, g:200, b:0}, RGB8 {r:255, g:0, b:0}];
+        let lines: Vec<Vec<(f32, f32)>> = (0..4)
+        .map(|base| report.positions.iter().enumerate().map(|(position, x)| (position as f32 + 1.0, x[base])).collect())
+        .collect();
+        println!("\ny = % of each base at each position (green = A, blue = C, yellow = G, red = T)");
+        Chart::new_with_y_range(self.plot_width, self.plot_height, 1.0, report.positions.len() as f32, 0.0, 100.0)
+        .linecolorplot(&Shape::Lines(&lines[0]), colors[0])
+        .linecolorplot(&Shape::Lines(&lines[1]), colors[1])
+        .linecolorplot(&Shape::Lines(&lines[2]), colors[2])
+        .linecolorplot(&Shape::Lines(&lines[3]), colors[3])
+        .display();
+    }
+
+    // Display the % of reads with each adapter
+    pub fn adapter_tables(& mut self) {
+        println!("Adapter\t\t\t\t% Reads");
+        for (name, percent) in &self.adapters.adapters {
+            println!("{:<32}{:.2}", name, percent);
+        }
+    }
+
+    // Display complexity summary, DUST distribution and top low complexity sequences
+    pub fn complexity_charts(& mut self) {
+        let report = &self.complexity;
+        println!("Low complexity reads (DUST > {}): {} ({:.2}%)", DUST_THRESHOLD, pretty_print_int(&report.low_complexity_reads), report.low_complexity_percent);
+        println!("\tMean\tMedian");
+        println!("DUST\t{:.2}\t{:.2}", report.dust_mean, report.dust_median);
+        println!("Entropy\t{:.3}\t{:.3}", report.entropy_mean, report.entropy_median);
+
+        let max_dust = report.dust_histogram.iter().rposition(|x| *x > 0).unwrap_or(0);
+        let points_for_dust: Vec<(f32, f32)> = (0..=max_dust + 1)
+        .map(|x| (x as f32, report.dust_histogram.get(x).cloned().unwrap_or(0) as f32))
+        .collect();
+        println!("\ny = Number of reads with DUST score x (0 = random, 100 = homopolymer)");
+        Chart::new(self.plot_width, self.plot_height, 0.0, max_dust as f32 + 1.0)
+        .lineplot(&Shape::Bars(&points_for_dust))
+        .display();
+
+        if !report.top_low_complexity.is_empty() {
+            println!("\nMost frequent low complexity sequences");
+            println!("Reads\tSequence (first 50 bases)");
+            for (sequence, count) in &report.top_low_complexity {
+                println!("{}\t{}", pretty_print_int(count), &sequence[..sequence.len().min(50)]);
+            }
+        }
     }
 
     pub fn duplication_calculation(& mut self) {
                 // Limit to 50 bp? like fastqc
-                let filtered_lines_to_get: Vec<String> = self.first_n_sequences
+                let mut filtered_lines_to_get: Vec<&str> = self.first_n_sequences
                 .par_iter()
                 .map(|x: &String| 
                     if x.len() < 50{
-                        x.to_owned()}else{
-                        x[0..50].to_owned()}
+                        x.as_str()}else{
+                        &x[0..50]}
                 )
                 .collect();
-            
-                let mut unique_lines = filtered_lines_to_get.clone();
-                unique_lines.sort();
-                unique_lines.dedup();
-            
+                filtered_lines_to_get.par_sort_unstable();
+
+                // Gives the number of times a sequence appear in our sample
+                let count_map: Vec<usize> = filtered_lines_to_get
+                .iter()
+                .dedup_with_count()
+                .map(|(count, _)| count)
+                .collect();
+
                 let full_length = filtered_lines_to_get.len();
-                let dedup_length = unique_lines.len();
-            
+                let dedup_length = count_map.len();
+
                 // Duplication levels
                 let dedup_percent: f32 = (dedup_length as f32 /full_length as f32) * 100.0;
-                
-                // println!("Before dedup: {} reads", pretty_print_int(&full_length));
-                // println!("After dedup: {} reads", pretty_print_int(&dedup_length));
-            
-                // println!("Reads are {:02.2}% duplicated ", 100.0 - dedup_percent);
-                // println!("{:02.2}% left if deduplicated", dedup_percent);
-
-                self.duplication_levels = dedup_percent;
-    }
 
-    // DIsplay a chart for duplication
-    pub fn duplication_chart(& mut self) -> () {
-                 // Limit to 50 bp? like fastqc
-                 let filtered_lines_to_get: Vec<String> = self.first_n_sequences
-                 .par_iter()
-                 .map(|x: &String| 
-                     if x.len() < 50{
-                         x.to_owned()}else{
-                         x[0..50].to_owned()}
-                 )
-                 .collect();
-
-                // itertools test
-                // Gives the number of times a sequence appear in our sample
-                // {(2, "ATGCGTCG"), 
-                //  (4, "TCGCCGCGGA"),
-                //  (1, "TCGCGCGAGGNTTA")}
-                let mut vector_for_map = filtered_lines_to_get.clone();
-                vector_for_map.sort();
-                let count_map: Vec<(usize, String)> = vector_for_map
-                .into_iter()
+                // Number of duplicated sequences seen x times, anything over 10 counts as 10
+                self.duplication_histogram = count_map
+                .iter()
+                .filter(|x| **x > 1)
+                .map(|x| (*x).min(10))
+                .sorted()
                 .dedup_with_count()
+                .map(|(sequences, times)| (times, sequences))
                 .collect();
-            
-                // Todo -> Bin all reads duplicated more than 10 times
-                // ONLY KEEP TUPLES WHEN ELEMENT 0 is more than 1 - Duplicated sequences
-                let dup_map: Vec<(usize, String)> = count_map
-                .into_par_iter()
-                .filter(|x| x.0 > 1)
-                .collect();
-                
 
-                // Only keep the first element of each tuple 
-                // Result should be {1, 2, 3, 2, 3, 4, 4, 1, ,1 ,1, 4, 2}
-                // Replace anything over 10 by 10
-                let occurence_vec: Vec<usize> = dup_map
-                .par_iter()
-                .map(|(number, _)| if number > &10 {10 as usize} else {number.to_owned()} )
-                .collect();
+                self.duplication_levels = dedup_percent;
+    }
 
-                let mut unique_occurrences: Vec<usize> = occurence_vec.clone();
-                unique_occurrences.sort();
-                unique_occurrences.dedup();
-            
-                
-                if unique_occurrences.is_empty() {
+    // DIsplay a chart for duplication
+    pub fn duplication_chart(& mut self) {
+                if self.duplication_histogram.is_empty() {
                     println!("No duplication detected!")
                 } else {
-            
-                // PANIC IF NO DUPLICATED AT ALL
-                    let max_occurrence = unique_occurrences.iter().max().unwrap().to_owned();
-                    let points_for_distribution_chart: Vec<(f32, f32)> = unique_occurrences
-                    .par_iter()
-                    .map(|x| (*x as f32, count_number_of_occurence(&occurence_vec, *x) as f32))
+                    let max_occurrence = self.duplication_histogram.last().unwrap().0;
+                    let points_for_distribution_chart: Vec<(f32, f32)> = self.duplication_histogram
+                    .iter()
+                    .map(|(times, sequences)| (*times as f32, *sequences as f32))
                     .collect();
-            
-            
+
                     println!("y = Number of reads duplicated x times");
                     Chart::new(self.plot_width, self.plot_height, 2.0, max_occurrence as f32) // Start duplication chart at x = 2 
                     .lineplot(&Shape::Lines(&points_for_distribution_chart))
@@ -276,45 +843,38 @@ impl FqObject{
         }
 
         // Display Quality Charts
-    pub fn quality_charts(& mut self) -> () {
-        let mut step = 1;
-            if self.isLongReads {step = 10} // if long read, sample every 10 base
-            
-            // Number of reads with that read length
-            let population_numbers: Vec<u32> = (1..self.len_max +1).step_by(step)
-            .into_iter()
-            .map(|x| sub_vector_above_this_length(&self.first_n_quality, x as usize))
-            .collect();
-        
-            // Sum of qualities for each positions
-            let sum_numbers: Vec<u32> = (0..self.len_max).step_by(step)
-            .into_iter()
-            .map(|x| sub_vector_sum_at_index(&self.first_n_quality, x as usize))
-            .collect();
+    pub fn quality_charts(& mut self) {
+            // With variable read lengths, do not plot positions covered by a handful of reads
+            let min_reads: usize = if self.platform.has_variable_length() {self.min_reads_per_bin} else {1};
 
             // Get the points for chart
-            let quality_points_for_chart: Vec<(f32, f32)> = (0..sum_numbers.len())
-            .into_iter()
-            .map(|x| (x as f32 * step as f32 + 1.0, (sum_numbers.get(x).unwrap().to_owned() as f32 / population_numbers.get(x).unwrap().to_owned() as f32))).
-            collect();
+            let quality_points_for_chart: Vec<(f32, f32)> = self.position_qualities
+            .iter()
+            .filter(|(_, _, reads)| *reads >= min_reads)
+            .map(|(position, mean, _)| (*position as f32, *mean))
+            .collect();
+            let last_position = quality_points_for_chart.last().map(|x| x.0).unwrap_or(self.len_max as f32);
         
             // Red
             let red = RGB8 {r:100, g:255, b:0};
-            let qual_threshold = 20 as f32;
+            let qual_threshold = 20_f32;
         
             println!("\ny = Mean quality score at each position (horizontal line = Q20)");
+            if self.platform.has_variable_length() {println!("Positions covered by fewer than {} reads are not shown", self.min_reads_per_bin)};
         
             // &Shape::Lines(&[(0.0, qual_threshold), (maximum.to_owned() as f32, qual_threshold)])
             //
-            Chart::new(self.plot_width, self.plot_height, 0.0, self.len_max as f32)
+            Chart::new(self.plot_width, self.plot_height, 0.0, last_position)
             .lineplot(&Shape::Bars(&quality_points_for_chart))
             .linecolorplot(&Shape::Continuous(Box::new(|_x| qual_threshold)), red)
             .display();
+
+            if self.platform.is_long_reads() {self.end_quality_charts()};
         
             // 2nd chart with mean quality per read
-            let mut mean_quality_vector: Vec<u32>  = self.first_n_quality
+            let mut mean_quality_vector: Vec<u32>  = self.read_mean_qualities
             .par_iter()
-            .map(|x| x.par_iter().sum::<u32>() / x.len() as u32)
+            .map(|x| x.round() as u32)
             .collect();
             mean_quality_vector.sort();
         
@@ -337,9 +897,112 @@ impl FqObject{
             Chart::new(self.plot_width, self.plot_height, min_quality as f32, max_quality as f32)
             .lineplot(&Shape::Lines(&point_for_mean_qual))
             .display();
+
+            // 3rd chart with the phred score of every base
+            // Keep the values that are never seen so that each bar is one phred score wide
+            // otherwise binned qualities (2, 12, 23, 37) are drawn as wide blocks
+            let max_base_quality = self.quality_alphabet.last().map(|x| x.0).unwrap_or(0);
+            let points_for_base_quality: Vec<(f32, f32)> = (0..=max_base_quality + 1)
+            .map(|q| (q as f32, self.quality_histogram.get(q as usize).cloned().unwrap_or(0) as f32))
+            .collect();
+            let max_base_quality = max_base_quality as f32;
+
+            println!("\ny = Number of bases with phred score x");
+            if self.quality_binning.is_some() {println!("Binned qualities: only {} distinct values", self.quality_alphabet.len())};
+            Chart::new(self.plot_width, self.plot_height, 0.0, max_base_quality + 1.0)
+            .lineplot(&Shape::Bars(&points_for_base_quality))
+            .display();
+        }
+
+        // Display yield statistics
+        pub fn yield_tables(& mut self) {
+            println!("Total yield: {} bases in {} reads", pretty_print_int(&(self.total_bases as usize)), pretty_print_int(&self.first_n_sequences.len()));
+            println!("N10\tN50\tN90\tL50");
+            println!("{}\t{}\t{}\t{}", self.n10, self.n50, self.n90, self.l50);
+            println!("\nLength\t\tReads\t% Reads\tBases\t% Bases");
+            let total_reads = self.first_n_sequences.len() as f32;
+            for (threshold, reads, bases) in &self.reads_above_thresholds {
+                println!(">= {}\t{}\t{:.2}\t{}\t{:.2}",
+                    pretty_print_int(&(*threshold as usize)),
+                    pretty_print_int(&(*reads as usize)), *reads as f32 / total_reads * 100.0,
+                    pretty_print_int(&(*bases as usize)), *bases as f32 / self.total_bases as f32 * 100.0);
+            }
+        }
+
+        // Display a length histogram weighted by bases on a log scale
+        pub fn yield_charts(& mut self) {
+            // 20 bins per log10 unit
+            let bins_per_decade = 20.0;
+            let mut bases_per_bin: Vec<u64> = vec![0; ((self.len_max.max(1) as f32).log10() * bins_per_decade) as usize + 1];
+            for (length, count) in self.length_histogram.iter().enumerate().skip(1) {
+                bases_per_bin[((length as f32).log10() * bins_per_decade) as usize] += length as u64 * count;
+            }
+            let points_for_yield: Vec<(f32, f32)> = bases_per_bin
+            .iter()
+            .enumerate()
+            .filter(|(_, bases)| **bases > 0)
+            .map(|(bin, bases)| (bin as f32 / bins_per_decade, *bases as f32))
+            .collect();
+
+            println!("\ny = Number of bases in reads of length x (x = log10 of read length, 3 = 1kb, 4 = 10kb)");
+            Chart::new(self.plot_width, self.plot_height, (self.len_min.max(1) as f32).log10().floor(), (self.len_max.max(1) as f32).log10() + 1.0 / bins_per_decade)
+            .lineplot(&Shape::Bars(&points_for_yield))
+            .display();
+        }
+
+        // Display read length vs mean read quality as a 2D density
+        pub fn density_chart(& mut self) {
+            let lengths: Vec<usize> = self.first_n_sequences.par_iter().map(|x| x.len()).collect();
+            let grid = length_quality_density(&lengths, &self.read_mean_qualities, self.plot_width as usize / 2, self.plot_height as usize / 4);
+            println!("\ny = Mean read quality, x = Read length (log scale)");
+            display_density(&grid);
+        }
+
+        // Display the distinct quality values and the binning scheme
+        pub fn quality_alphabet_table(& mut self) {
+            println!("Quality alphabet: {} distinct values", self.quality_alphabet.len());
+            match &self.quality_binning {
+                Some(scheme) => println!("Binned qualities: {}", scheme),
+                None => println!("Qualities are not binned"),
+            }
+            let total: u64 = self.quality_alphabet.iter().map(|x| x.1).sum();
+            println!("Q\tChar\tBases\t%");
+            for (q, count) in &self.quality_alphabet {
+                println!("{}\t{}\t{}\t{:.2}", q, char::from_u32(q + 33).unwrap_or('?'), pretty_print_int(&(*count as usize)), *count as f32 / total as f32 * 100.0);
+            }
+        }
+
+        // Display quality profiles anchored at the 5' and at the 3' end of the reads
+        pub fn end_quality_charts(& mut self) {
+            let window = self.end_profile_length.min(self.len_max as usize);
+            let bin_size = self.end_profile_bin_size;
+            let red = RGB8 {r:100, g:255, b:0};
+            let qual_threshold = 20_f32;
+
+            for (profile, from_3_prime) in [(&self.five_prime_profile, false), (&self.three_prime_profile, true)] {
+                // x is the distance from the 5' end or minus the distance from the 3' end
+                let points_for_chart: Vec<(f32, f32)> = profile
+                .iter()
+                .filter(|(_, _, reads)| *reads >= self.min_reads_per_bin)
+                .map(|(position, mean, _)| (if from_3_prime {-(*position as f32)} else {*position as f32}, *mean))
+                .collect();
+                if points_for_chart.is_empty() {continue}
+
+                let (x_min, x_max) = if from_3_prime {(points_for_chart.last().unwrap().0, 0.0)} else {(0.0, points_for_chart.last().unwrap().0)};
+                if from_3_prime {
+                    println!("\ny = Mean quality score from the 3' end (last {} bases, x = - distance to the 3' end)", pretty_print_int(&window));
+                } else {
+                    println!("\ny = Mean quality score from the 5' end (first {} bases)", pretty_print_int(&window));
+                }
+                println!("{} bases per bin, bins covered by fewer than {} reads are not shown", bin_size, self.min_reads_per_bin);
+                Chart::new(self.plot_width, self.plot_height, x_min, x_max)
+                .lineplot(&Shape::Bars(&points_for_chart))
+                .linecolorplot(&Shape::Continuous(Box::new(|_x| qual_threshold)), red)
+                .display();
+            }
         }
 
-        pub fn length_charts(& mut self) -> () {
+        pub fn length_charts(& mut self) {
             let size_of_filtered_lines: Vec<usize> = self.first_n_sequences
             .par_iter()
             .map(|x: &String| x.len())
@@ -0,0 +1,446 @@
+use std::fmt::Write as _;
+use std::io::Write;
+use std::path::Path;
+use itertools::Itertools;
+use rayon::prelude::*;
+use super::fastq::FqObject;
+use super::func::{read_quality_histogram, histogram_quantile};
+use super::verdict::{Verdict, verdict_above, verdict_below};
+use super::platform::PHRED64;
+
+// FASTQC COMPATIBLE OUTPUT
+// <sample>_fastqc/fastqc_data.txt and summary.txt with the FastQC >>Module sections, column headers
+// and pass / warn / fail tags, so MultiQC and FastQC based pipelines can read rsFQC results
+// Optionally zipped as <sample>_fastqc.zip like FastQC does
+// Positions are grouped like FastQC for reads longer than 75 bp (1 to 9, then ranges)
+
+const FASTQC_VERSION: &str = "0.12.1";
+const QUALITY_BINS: usize = 94; // phred 0 - 93
+const DUPLICATION_LENGTH: usize = 50; // same as duplication_calculation
+const DUPLICATION_LEVELS: [&str; 16] = ["1", "2", "3", "4", "5", "6", "7", "8", "9", ">10", ">50", ">100", ">500", ">1k", ">5k", ">10k+"];
+const OVERREPRESENTED_PERCENT: f32 = 0.1;
+
+/// FastQC module tag
+fn tag(verdict: Verdict) -> &'static str {
+    match verdict {Verdict::Pass => "pass", Verdict::Warn => "warn", Verdict::Fail => "fail"}
+}
+
+/// Group size for positions after the 9th, FastQC keeps fewer than 75 groups
+fn linear_interval(length: usize) -> usize {
+    let mut multiplier = 1;
+    loop {
+        for base in [2, 5, 10] {
+            let interval = base * multiplier;
+            if 9 + (length - 9).div_ceil(interval) < 75 {
+                return interval
+            }
+        }
+        multiplier *= 10;
+    }
+}
+
+/// FastQC position groups, 1 based and inclusive
+fn base_groups(max_length: usize) -> Vec<(usize, usize)> {
+    if max_length <= 75 {
+        return (1..=max_length).map(|x| (x, x)).collect()
+    }
+    let interval = linear_interval(max_length);
+    let mut groups: Vec<(usize, usize)> = (1..=9).map(|x| (x, x)).collect();
+    let mut start = 10;
+    while start <= max_length {
+        groups.push((start, (start + interval - 1).min(max_length)));
+        start += interval;
+    }
+    groups
+}
+
+fn group_label(group: &(usize, usize)) -> String {
+    if group.0 == group.1 {group.0.to_string()} else {format!("{}-{}", group.0, group.1)}
+}
+
+/// Group index of each position (0 based)
+fn group_of_positions(groups: &[(usize, usize)]) -> Vec<usize> {
+    groups.iter().enumerate().flat_map(|(index, (start, end))| std::iter::repeat_n(index, end - start + 1)).collect()
+}
+
+/// Quality histogram of each position group
+fn group_quality_counts(qualities: &[Vec<u32>], group_of: &[usize], number_of_groups: usize) -> Vec<Vec<u64>> {
+    qualities.par_iter()
+    .fold(|| vec![vec![0u64; QUALITY_BINS]; number_of_groups], |mut counts, quality| {
+        for (q, group) in quality.iter().zip(group_of) {
+            counts[*group][(*q as usize).min(QUALITY_BINS - 1)] += 1;
+        }
+        counts
+    })
+    .reduce(|| vec![vec![0u64; QUALITY_BINS]; number_of_groups], |mut counts, other| {
+        for (group, other) in counts.iter_mut().zip(other) {
+            for (count, other) in group.iter_mut().zip(other) {*count += other};
+        }
+        counts
+    })
+}
+
+/// A C G T N counts of each position group and number of reads at each GC %
+fn group_base_counts(sequences: &[String], group_of: &[usize], number_of_groups: usize) -> (Vec<[u64; 5]>, Vec<u64>) {
+    sequences.par_iter()
+    .fold(|| (vec![[0u64; 5]; number_of_groups], vec![0u64; 101]), |(mut counts, mut gc), sequence| {
+        let mut read = [0u64; 5];
+        for (base, group) in sequence.bytes().zip(group_of) {
+            let index = match base {b'A' | b'a' => 0, b'C' | b'c' => 1, b'G' | b'g' => 2, b'T' | b't' => 3, _ => 4};
+            counts[*group][index] += 1;
+            read[index] += 1;
+        }
+        let acgt: u64 = read[..4].iter().sum();
+        if acgt > 0 {gc[((read[1] + read[2]) as f64 / acgt as f64 * 100.0).round() as usize] += 1};
+        (counts, gc)
+    })
+    .reduce(|| (vec![[0u64; 5]; number_of_groups], vec![0u64; 101]), |(mut counts, mut gc), (other_counts, other_gc)| {
+        for (group, other) in counts.iter_mut().zip(other_counts) {
+            for i in 0..5 {group[i] += other[i]};
+        }
+        for (count, other) in gc.iter_mut().zip(other_gc) {*count += other};
+        (counts, gc)
+    })
+}
+
+/// Reads where each adapter starts in each position group
+fn group_adapter_starts(sequences: &[String], adapters: &[String], group_of: &[usize], number_of_groups: usize) -> Vec<Vec<u64>> {
+    sequences.par_iter()
+    .fold(|| vec![vec![0u64; number_of_groups]; adapters.len()], |mut starts, sequence| {
+        let sequence = sequence.to_ascii_uppercase();
+        for (adapter, starts) in adapters.iter().zip(starts.iter_mut()) {
+            if let Some(group) = sequence.find(adapter.as_str()).and_then(|x| group_of.get(x)) {starts[*group] += 1};
+        }
+        starts
+    })
+    .reduce(|| vec![vec![0u64; number_of_groups]; adapters.len()], |mut starts, other| {
+        for (adapter, other) in starts.iter_mut().zip(other) {
+            for (count, other) in adapter.iter_mut().zip(other) {*count += other};
+        }
+        starts
+    })
+}
+
+/// % of the reads that are off a normal distribution fitted to the GC histogram
+fn gc_deviation(gc: &[u64]) -> f32 {
+    let total: u64 = gc.iter().sum();
+    if total == 0 {
+        return 0.0
+    }
+    let mean = gc.iter().enumerate().map(|(x, count)| x as f64 * *count as f64).sum::<f64>() / total as f64;
+    let variance = gc.iter().enumerate().map(|(x, count)| (x as f64 - mean).powi(2) * *count as f64).sum::<f64>() / total as f64;
+    let sd = variance.sqrt().max(0.5);
+    let deviation: f64 = gc.iter().enumerate()
+    .map(|(x, count)| {
+        let expected = total as f64 * (-((x as f64 - mean) / sd).powi(2) / 2.0).exp() / (sd * (2.0 * std::f64::consts::PI).sqrt());
+        (*count as f64 - expected).abs()
+    })
+    .sum();
+    (deviation / total as f64 * 100.0) as f32
+}
+
+/// Duplication level index of a sequence seen this many times
+fn duplication_level(count: usize) -> usize {
+    match count {
+        1..=9 => count - 1,
+        10..=49 => 9,
+        50..=99 => 10,
+        100..=499 => 11,
+        500..=999 => 12,
+        1000..=4999 => 13,
+        5000..=9999 => 14,
+        _ => 15,
+    }
+}
+
+/// FastQC total bases format
+fn format_bases(bases: u64) -> String {
+    match bases {
+        0..=999 => format!("{} bp", bases),
+        1000..=999_999 => format!("{:.1} kbp", bases as f64 / 1e3),
+        1_000_000..=999_999_999 => format!("{:.1} Mbp", bases as f64 / 1e6),
+        _ => format!("{:.1} Gbp", bases as f64 / 1e9),
+    }
+}
+
+/// Contents of fastqc_data.txt and the (module, tag) list for summary.txt
+pub fn fastqc_data(fq: &FqObject) -> (String, Vec<(&'static str, Verdict)>) {
+    let reads = fq.first_n_quality.len();
+    let groups = base_groups(fq.len_max as usize);
+    let group_of = group_of_positions(&groups);
+    let thresholds = &fq.thresholds;
+    let mut data = format!("##FastQC\t{}\n", FASTQC_VERSION);
+    let mut summary: Vec<(&'static str, Verdict)> = Vec::new();
+    let mut module = |data: &mut String, name: &'static str, verdict: Verdict, body: &str| {
+        let _ = write!(data, ">>{}\t{}\n{}>>END_MODULE\n", name, tag(verdict), body);
+        summary.push((name, verdict));
+    };
+
+    // Basic Statistics
+    let filename = Path::new(&fq.filepath).file_name().and_then(|x| x.to_str()).unwrap_or(&fq.filepath);
+    let length = if fq.len_min == fq.len_max {fq.len_min.to_string()} else {format!("{}-{}", fq.len_min, fq.len_max)};
+    let flagged = if fq.exclude_filtered {fq.reads_filtered} else {0};
+    let body = format!("#Measure\tValue\nFilename\t{}\nFile type\tConventional base calls\nEncoding\t{}\nTotal Sequences\t{}\nTotal Bases\t{}\nSequences flagged as poor quality\t{}\nSequence length\t{}\n%GC\t{}\n",
+        filename, if fq.quality_encoding == PHRED64 {"Illumina 1.5"} else {"Sanger / Illumina 1.9"}, reads, format_bases(fq.total_bases), flagged, length, fq.content.gc_percent.round() as u32);
+    module(&mut data, "Basic Statistics", Verdict::Pass, &body);
+
+    // Per base sequence quality
+    let quality_counts = group_quality_counts(&fq.first_n_quality, &group_of, groups.len());
+    let mut body = String::from("#Base\tMean\tMedian\tLower Quartile\tUpper Quartile\t10th Percentile\t90th Percentile\n");
+    for (group, counts) in groups.iter().zip(&quality_counts) {
+        let total: u64 = counts.iter().sum();
+        if total == 0 {continue};
+        let mean = counts.iter().enumerate().map(|(q, count)| q as f64 * *count as f64).sum::<f64>() / total as f64;
+        let _ = writeln!(body, "{}\t{}\t{:.1}\t{:.1}\t{:.1}\t{:.1}\t{:.1}", group_label(group), mean,
+            histogram_quantile(counts, 0.5), histogram_quantile(counts, 0.25), histogram_quantile(counts, 0.75), histogram_quantile(counts, 0.1), histogram_quantile(counts, 0.9));
+    }
+    module(&mut data, "Per base sequence quality", fq.verdicts.quality.unwrap_or_default(), &body);
+
+    // Per tile sequence quality - deviation from the mean of all tiles
+    if !fq.tile_deviations.is_empty() {
+        let single_lane = fq.lane_deviations.len() <= 1;
+        let mut body = String::from("#Tile\tBase\tMean\n");
+        for tile in &fq.tile_deviations {
+            let name = if single_lane {tile.tile.to_string()} else {format!("{}:{}", tile.lane, tile.tile)};
+            for (bin, deviation) in tile.deviations.iter().enumerate() {
+                let start = bin * fq.tile_bin_size + 1;
+                let _ = writeln!(body, "{}\t{}\t{}", name, group_label(&(start, start + fq.tile_bin_size - 1)), deviation);
+            }
+        }
+        let worst = fq.tile_deviations.iter().map(|x| x.worst_deviation).fold(0.0, f32::min);
+        module(&mut data, "Per tile sequence quality", verdict_above(-worst, 2.0, 5.0), &body);
+    }
+
+    // Per sequence quality scores
+    let histogram = read_quality_histogram(&fq.read_mean_qualities);
+    let mode = histogram.iter().max_by_key(|(_, count)| *count).map(|(q, _)| *q).unwrap_or(0);
+    let body: String = std::iter::once("#Quality\tCount\n".to_string())
+    .chain(histogram.iter().map(|(q, count)| format!("{}\t{}\n", q, count)))
+    .collect();
+    let (quality_warn, quality_fail) = thresholds.quality(fq.platform);
+    module(&mut data, "Per sequence quality scores", verdict_below(mode as f32, quality_warn, quality_fail), &body);
+
+    // Base composition
+    let (base_counts, gc) = if fq.verdicts.content.is_some() || fq.verdicts.n.is_some() {
+        group_base_counts(&fq.first_n_sequences, &group_of, groups.len())
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    if let Some(content_verdict) = fq.verdicts.content {
+        let mut body = String::from("#Base\tG\tA\tT\tC\n");
+        for (group, counts) in groups.iter().zip(&base_counts) {
+            let acgt: u64 = counts[..4].iter().sum();
+            if acgt == 0 {continue};
+            let percent = |i: usize| counts[i] as f64 / acgt as f64 * 100.0;
+            let _ = writeln!(body, "{}\t{}\t{}\t{}\t{}", group_label(group), percent(2), percent(0), percent(3), percent(1));
+        }
+        module(&mut data, "Per base sequence content", content_verdict, &body);
+
+        let body: String = std::iter::once("#GC Content\tCount\n".to_string())
+        .chain(gc.iter().enumerate().map(|(x, count)| format!("{}\t{}\n", x, count)))
+        .collect();
+        module(&mut data, "Per sequence GC content", verdict_above(gc_deviation(&gc), 15.0, 30.0), &body);
+    }
+    if let Some(n_verdict) = fq.verdicts.n {
+        let mut body = String::from("#Base\tN-Count\n");
+        for (group, counts) in groups.iter().zip(&base_counts) {
+            let all: u64 = counts.iter().sum();
+            if all == 0 {continue};
+            let _ = writeln!(body, "{}\t{}", group_label(group), counts[4] as f64 / all as f64 * 100.0);
+        }
+        module(&mut data, "Per base N content", n_verdict, &body);
+    }
+
+    // Sequence Length Distribution
+    let mut body = String::from("#Length\tCount\n");
+    for group in groups.iter().filter(|(_, end)| *end >= fq.len_min as usize) {
+        let count: u64 = fq.length_histogram.iter().take(group.1 + 1).skip(group.0).sum();
+        let _ = writeln!(body, "{}\t{}", group_label(group), count);
+    }
+    module(&mut data, "Sequence Length Distribution", fq.verdicts.length.unwrap_or_default(), &body);
+
+    // Sequence Duplication Levels and Overrepresented sequences - first 50 bases like duplication_calculation
+    if let Some(duplication_verdict) = fq.verdicts.duplication {
+        let counts: Vec<(usize, &str)> = fq.first_n_sequences.iter()
+        .map(|x| &x[..x.len().min(DUPLICATION_LENGTH)])
+        .sorted_unstable()
+        .dedup_with_count()
+        .collect();
+        let mut levels = [(0usize, 0usize); 16]; // distinct sequences, reads
+        for (count, _) in &counts {
+            let level = &mut levels[duplication_level(*count)];
+            level.0 += 1;
+            level.1 += count;
+        }
+        let mut body = format!("#Total Deduplicated Percentage\t{}\n#Duplication Level\tPercentage of deduplicated\tPercentage of total\n", fq.duplication_levels);
+        for (name, (distinct, total)) in DUPLICATION_LEVELS.iter().zip(levels) {
+            let _ = writeln!(body, "{}\t{}\t{}", name, distinct as f64 / counts.len().max(1) as f64 * 100.0, total as f64 / reads.max(1) as f64 * 100.0);
+        }
+        module(&mut data, "Sequence Duplication Levels", duplication_verdict, &body);
+
+        let overrepresented: Vec<(usize, &str, f32)> = counts.iter()
+        .map(|(count, sequence)| (*count, *sequence, *count as f32 / reads.max(1) as f32 * 100.0))
+        .filter(|(_, _, percent)| *percent > OVERREPRESENTED_PERCENT)
+        .sorted_by(|a, b| b.0.cmp(&a.0))
+        .collect();
+        let mut body = String::from("#Sequence\tCount\tPercentage\tPossible Source\n");
+        for (count, sequence, percent) in &overrepresented {
+            let source = fq.adapter_list.iter()
+            .find(|x| sequence.to_ascii_uppercase().contains(&x.sequence.to_ascii_uppercase()))
+            .map(|x| x.name.clone())
+            .unwrap_or("No Hit".to_string());
+            let _ = writeln!(body, "{}\t{}\t{}\t{}", sequence, count, percent, source);
+        }
+        let highest = overrepresented.first().map(|x| x.2).unwrap_or(0.0);
+        module(&mut data, "Overrepresented sequences", verdict_above(highest, OVERREPRESENTED_PERCENT, 1.0), &body);
+    }
+
+    // Adapter Content - cumulative % of reads with the adapter starting at or before each position
+    if let Some(adapters_verdict) = fq.verdicts.adapters {
+        let adapters: Vec<String> = fq.adapter_list.iter().map(|x| x.sequence.to_ascii_uppercase()).collect();
+        let starts = group_adapter_starts(&fq.first_n_sequences, &adapters, &group_of, groups.len());
+        let mut body = format!("#Position\t{}\n", fq.adapter_list.iter().map(|x| x.name.as_str()).join("\t"));
+        let mut cumulative = vec![0u64; adapters.len()];
+        for (index, group) in groups.iter().enumerate() {
+            let _ = write!(body, "{}", group_label(group));
+            for (total, starts) in cumulative.iter_mut().zip(&starts) {
+                *total += starts[index];
+                let _ = write!(body, "\t{}", *total as f64 / reads.max(1) as f64 * 100.0);
+            }
+            body.push('\n');
+        }
+        module(&mut data, "Adapter Content", adapters_verdict, &body);
+    }
+    (data, summary)
+}
+
+/// Write <sample>_fastqc/ (and <sample>_fastqc.zip), returns the paths written
+pub fn write_fastqc(fq: &FqObject, zip: bool) -> Result<Vec<String>, String> {
+    let name = format!("{}_fastqc", fq.sample);
+    let filename = Path::new(&fq.filepath).file_name().and_then(|x| x.to_str()).unwrap_or(&fq.filepath).to_string();
+    let (data, modules) = fastqc_data(fq);
+    let summary: String = modules.iter().map(|(module, verdict)| format!("{}\t{}\t{}\n", verdict.name(), module, filename)).collect();
+
+    std::fs::create_dir_all(&name).map_err(|e| e.to_string())?;
+    std::fs::write(Path::new(&name).join("fastqc_data.txt"), &data).map_err(|e| e.to_string())?;
+    std::fs::write(Path::new(&name).join("summary.txt"), &summary).map_err(|e| e.to_string())?;
+    let mut written = vec![name.clone()];
+
+    if zip {
+        let path = format!("{}.zip", name);
+        let file = std::fs::File::create(&path).map_err(|e| e.to_string())?;
+        let mut archive = zip::ZipWriter::new(file);
+        let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        archive.add_directory(format!("{}/", name), options).map_err(|e| e.to_string())?;
+        for (file, contents) in [("fastqc_data.txt", &data), ("summary.txt", &summary)] {
+            archive.start_file(format!("{}/{}", name, file), options).map_err(|e| e.to_string())?;
+            archive.write_all(contents.as_bytes()).map_err(|e| e.to_string())?;
+        }
+        archive.finish().map_err(|e| e.to_string())?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::verdict::Verdicts;
+    use super::super::config::Adapter;
+
+    #[test]
+    fn base_groups_like_fastqc() {
+        assert_eq!(base_groups(75).len(), 75);
+        assert_eq!(base_groups(75)[74], (75, 75));
+        assert_eq!(linear_interval(150), 5);
+        let groups = base_groups(150);
+        assert_eq!(&groups[8..11], &[(9, 9), (10, 14), (15, 19)]);
+        assert_eq!(groups.last(), Some(&(150, 150)));
+        assert_eq!(linear_interval(10_000), 200);
+        let groups = base_groups(10_000);
+        assert_eq!(groups.len(), 59);
+        assert_eq!(groups[9], (10, 209));
+        assert_eq!(groups.last(), Some(&(9810, 10_000)));
+        assert_eq!(group_of_positions(&groups).len(), 10_000);
+    }
+
+    #[test]
+    fn duplication_level_boundaries() {
+        assert_eq!(duplication_level(1), 0);
+        assert_eq!((duplication_level(9), duplication_level(10)), (8, 9));
+        assert_eq!((duplication_level(49), duplication_level(50)), (9, 10));
+        assert_eq!((duplication_level(9999), duplication_level(10_000)), (14, 15));
+        assert_eq!(DUPLICATION_LEVELS[15], ">10k+");
+    }
+
+    #[test]
+    fn total_bases_format() {
+        assert_eq!(format_bases(999), "999 bp");
+        assert_eq!(format_bases(1000), "1.0 kbp");
+        assert_eq!(format_bases(1_500_000), "1.5 Mbp");
+        assert_eq!(format_bases(2_000_000_000), "2.0 Gbp");
+    }
+
+    fn processed(sequences: &[&str]) -> FqObject {
+        let mut fq = FqObject::new("/data/test.fq.gz".to_string(), 10);
+        fq.first_n_sequences = sequences.iter().map(|x| x.to_string()).collect();
+        fq.first_n_quality = sequences.iter().map(|x| vec![30; x.len()]).collect();
+        fq.read_mean_qualities = vec![30.0; sequences.len()];
+        fq.len_min = sequences.iter().map(|x| x.len()).min().unwrap() as u32;
+        fq.len_max = sequences.iter().map(|x| x.len()).max().unwrap() as u32;
+        fq.length_histogram = vec![0; fq.len_max as usize + 1];
+        for x in sequences {fq.length_histogram[x.len()] += 1};
+        fq.total_bases = sequences.iter().map(|x| x.len() as u64).sum();
+        fq.duplication_levels = 50.0;
+        fq.adapter_list = vec![Adapter {name: "Illumina Universal Adapter".to_string(), sequence: "AGATCGGAAGAG".to_string()}];
+        fq.verdicts = Verdicts {quality: Some(Verdict::Pass), length: Some(Verdict::Pass), duplication: Some(Verdict::Fail),
+            content: Some(Verdict::Warn), adapters: Some(Verdict::Pass), n: Some(Verdict::Pass)};
+        fq
+    }
+
+    #[test]
+    fn fastqc_data_modules_and_headers() {
+        let (data, summary) = fastqc_data(&processed(&["ACGTNACGTAGATCGGAAGAG", "ACGTNACGTAGATCGGAAGAG", "TTTTGGGGCCCCAAAATTTTG"]));
+        let lines: Vec<&str> = data.lines().collect();
+        assert_eq!(lines[0], "##FastQC\t0.12.1");
+        let expected = [
+            ("Basic Statistics", "pass", "#Measure\tValue"),
+            ("Per base sequence quality", "pass", "#Base\tMean\tMedian\tLower Quartile\tUpper Quartile\t10th Percentile\t90th Percentile"),
+            ("Per sequence quality scores", "pass", "#Quality\tCount"),
+            ("Per base sequence content", "warn", "#Base\tG\tA\tT\tC"),
+            ("Per sequence GC content", "", "#GC Content\tCount"),
+            ("Per base N content", "pass", "#Base\tN-Count"),
+            ("Sequence Length Distribution", "pass", "#Length\tCount"),
+            ("Sequence Duplication Levels", "fail", "#Total Deduplicated Percentage\t50"),
+            ("Overrepresented sequences", "", "#Sequence\tCount\tPercentage\tPossible Source"),
+            ("Adapter Content", "pass", "#Position\tIllumina Universal Adapter"),
+        ];
+        let starts: Vec<usize> = lines.iter().positions(|x| x.starts_with(">>") && *x != ">>END_MODULE").collect();
+        assert_eq!(starts.len(), expected.len());
+        for (start, (name, tag, header)) in starts.iter().zip(expected) {
+            let (module, module_tag) = lines[*start][2..].split_once('\t').unwrap();
+            assert_eq!(module, name);
+            assert!(["pass", "warn", "fail"].contains(&module_tag));
+            if !tag.is_empty() {assert_eq!(module_tag, tag)};
+            assert_eq!(lines[start + 1], header);
+            // Each module is closed before the next one
+            let end = lines[*start..].iter().position(|x| *x == ">>END_MODULE").unwrap() + start;
+            assert!(starts.iter().all(|x| x <= start || *x > end));
+        }
+        assert_eq!(*lines.last().unwrap(), ">>END_MODULE");
+        assert!(data.contains("Filename\ttest.fq.gz\n"));
+        assert!(data.contains("Sequence length\t21\n"));
+        assert!(lines.iter().any(|x| x.starts_with("ACGTNACGTAGATCGGAAGAG\t2\t") && x.ends_with("\tIllumina Universal Adapter")));
+        assert_eq!(summary.len(), expected.len());
+    }
+
+    #[test]
+    fn n_content_does_not_need_the_content_module() {
+        let mut fq = processed(&["ACGTNACGTA", "ACGTNACGTA"]);
+        fq.verdicts.content = None;
+        let (data, _) = fastqc_data(&fq);
+        assert!(!data.contains(">>Per base sequence content"));
+        assert!(data.contains(">>Per base N content\tpass\n"));
+        assert!(data.contains("5\t100\n"));
+    }
+}
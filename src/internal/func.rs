@@ -1,40 +1,26 @@
-use std::{fs::File, io::{Read, BufReader, BufRead, Write}, path::PathBuf};
+use std::{fs::File, io::{Read, BufReader, BufRead}, path::PathBuf};
+use std::sync::{Mutex, atomic::{AtomicUsize, Ordering}};
 use flate2::read::GzDecoder;
 use super::fastq::FqObject;
 use rayon::prelude::*;
 
 /// Function to detect the encoding of fq file -> returns either "gzip" or "text"
 pub fn get_encoding(filepath: &String) -> bool{
-    //Use first 2 bytes (byte1 == 0x1f) && (byte2 == 0x8b) 
-    let mut f = File::open(filepath).unwrap();
+    //Use first 2 bytes (byte1 == 0x1f) && (byte2 == 0x8b) - unreadable or shorter files are plain text
     let mut buf = [0u8; 2];
-    f.read_exact(&mut buf).unwrap();
-
-    let byte_1 = format!("0x{:02x}", buf[0]);
-    let byte_2 = format!("0x{:02x}", buf[1]);
-
-    if (byte_1 == "0x1f") && (byte_2 == "0x8b"){
-        // println!("Detected gzip encoding");
-        return true
-    } else{
-        // println!("Detected plain text encoding");
-        return false
-    }
+    let read = File::open(filepath).and_then(|mut f| f.read_exact(&mut buf));
+    read.is_ok() && buf == [0x1f, 0x8b]
 }
 
 /// Function to test quickly if the file is actually a fastQ file
 pub fn is_fastq_file(fqobject: &FqObject) -> bool {
     // Get first 3 lines
     let lines_to_test = buffer_to_fq_lines(&fqobject.filepath, 3, fqobject.gzipped);
-    // Check if respect fastq format
-    let first = lines_to_test[0].chars().nth(0).unwrap_or_else(|| '.');
-    let second = lines_to_test[2].chars().nth(0).unwrap_or_else(|| '.');
+    // Check if respect fastq format - at least one complete record
+    let first = lines_to_test.first().and_then(|x| x.chars().next()).unwrap_or('.');
+    let second = lines_to_test.get(2).and_then(|x| x.chars().next()).unwrap_or('.');
     // Exit if not fastq file
-    if first == '@' && second == '+' {
-        return true;
-    } else {
-        return false;
-    }
+    first == '@' && second == '+' && lines_to_test.len() >= 4
 }
 
 /// Given a filename , a number of records to get and a gzip flag
@@ -55,7 +41,7 @@ pub fn buffer_to_fq_lines(filepath: &String, number_of_records_to_get: usize, gz
         let reader: BufReader<File> = BufReader::new(file);
         lines_to_test.extend(reader.lines().take( number_of_records_to_get * 4 ).map(|x: Result<String, std::io::Error>| x.unwrap()).collect::<Vec<String>>());
     } 
-    return lines_to_test
+    lines_to_test
 }
 
 /// Get the first n quality records of a fastq file
@@ -69,7 +55,19 @@ pub fn get_first_n_quality_lines_of_fastq_file(fqobject: &FqObject) -> Vec<Vec<u
 
     let filtered_lines_to_quality: Vec<Vec<u32>> = filtered_lines_to_get.par_iter().map(|x| quality_vector_from_line(x.to_string())).collect();
 
-    return filtered_lines_to_quality;
+    filtered_lines_to_quality
+}
+
+/// Get the first n header records of a fastq file
+pub fn get_first_n_header_lines_of_fastq_file(fqobject: &FqObject) -> Vec<String>{
+    // Get first N records
+    let lines_to_test: Vec<String> = buffer_to_fq_lines(&fqobject.filepath, fqobject.number_of_records_used, fqobject.gzipped);
+    // Get the index of lines we need // Modulo 0 for header lines
+    let index_to_get: Vec<usize> = (0..(fqobject.number_of_records_used*4)).step_by(4).collect();  
+    // Filter first N records based on the index of previous step
+    let filtered_lines_to_get: Vec<String> = index_to_get.par_iter().filter_map(|&index| lines_to_test.get(index).cloned()).collect();
+
+    filtered_lines_to_get
 }
 
 /// Get the first n sequence records of a fastq file
@@ -81,19 +79,57 @@ pub fn get_first_n_sequence_lines_of_fastq_file(fqobject: &FqObject) -> Vec<Stri
     // Filter first N records based on the index of previous step
     let filtered_lines_to_get: Vec<String> = index_to_get.par_iter().filter_map(|&index| lines_to_test.get(index).cloned()).collect();
 
-    return filtered_lines_to_get;
+    filtered_lines_to_get
 }
 
-/// Character to quality
-pub fn char_to_qual(c: char)-> u32{
-    let ascii_code = c as u32;
-    return ascii_code-33
+/// Character to quality, characters below '!' are Q0
+pub fn char_to_qual(c: u8)-> u32{
+    (c as u32).saturating_sub(33)
 }
 
-/// Quality line to vector of quality
+/// Quality line to vector of quality, in base order
 pub fn quality_vector_from_line(line: String)-> Vec<u32>{
-    let return_value: Vec<u32> = line.chars().into_iter().par_bridge().map(|x| char_to_qual(x)).collect();
-    return return_value
+    let return_value: Vec<u32> = line.bytes().map(char_to_qual).collect();
+    return_value
+}
+
+/// File name without the directory and the .gz / .fastq / .fq extensions
+pub fn sample_name(filepath: &str) -> String {
+    let mut name = std::path::Path::new(filepath).file_name().and_then(|x| x.to_str()).unwrap_or(filepath);
+    for extension in [".gz", ".fastq", ".fq"] {
+        name = name.strip_suffix(extension).unwrap_or(name);
+    }
+    name.to_string()
+}
+
+/// Give files sharing a sample name a unique one, prefixed with their closest distinct directories
+/// (run1/S1.fq.gz and run2/S1.fq.gz become run1_S1 and run2_S1), numbered if that is not enough
+pub fn unique_sample_names(input: &mut [FqObject]) {
+    let names: Vec<String> = input.iter().map(|x| x.sample.clone()).collect();
+    let shared: std::collections::BTreeSet<&String> = names.iter().filter(|x| names.iter().filter(|y| y == x).count() > 1).collect();
+    for name in shared {
+        let files: Vec<usize> = (0..input.len()).filter(|i| &input[*i].sample == name).collect();
+        let directories: Vec<Vec<String>> = files.iter().map(|i| {
+            std::path::Path::new(&input[*i].filepath).parent().map(|x| {
+                x.components().filter_map(|x| x.as_os_str().to_str()).filter(|x| !["/", ".", ".."].contains(x)).map(|x| x.to_string()).rev().collect()
+            }).unwrap_or_default()
+        }).collect();
+        let depth = directories.iter().map(|x| x.len()).max().unwrap_or(0);
+        let candidates = |depth: usize| -> Vec<String> {
+            directories.iter().map(|x| {
+                let mut parts: Vec<String> = x.iter().take(depth).rev().cloned().collect();
+                parts.push(name.clone());
+                parts.join("_")
+            }).collect()
+        };
+        let unique = |candidates: &Vec<String>| candidates.iter().all(|x| candidates.iter().filter(|y| *y == x).count() == 1 && !names.iter().any(|y| y == x && x != name));
+        let new_names = (1..=depth).map(candidates).find(unique)
+            .unwrap_or_else(|| (1..=files.len()).map(|i| format!("{}_{}", name, i)).collect());
+        println!("{} files are named {}, their reports are written as {}", files.len(), name, new_names.join(", "));
+        for (i, new_name) in files.iter().zip(new_names) {
+            input[*i].sample = new_name;
+        }
+    }
 }
 
 /// CHeck if it's a file and if I can read it
@@ -104,7 +140,7 @@ pub fn check_file(filepath: &String) -> bool {
     // Weed out directory
     if  PathBuf::from(filepath).is_dir() {is_file = false};
 
-    return is_file
+    is_file
 
 }
 
@@ -132,7 +168,7 @@ pub fn is_readable(filepath: &String, gzipped: bool) -> bool {
             Err(_e) => {false},
         }
     }   
-    return _is_it_readable 
+    _is_it_readable 
 }
 
 /// Stolen from Michael Hall https://stackoverflow.com/questions/26998485/is-it-possible-to-print-a-number-formatted-with-thousand-separator-in-rust
@@ -146,60 +182,341 @@ pub fn pretty_print_int(i: &usize) -> String {
         }
         s.insert(0, val);
     }
-    return s;
+    s
 }
 
 /// Count number of occurence of something in a vector
 pub fn count_number_of_occurence(initial_vec: &Vec<usize>, item: usize)-> f32{
     let tmp: usize = initial_vec
     .par_iter()
-    .map(|x: &usize| x.to_owned() == item)
+    .map(|x: &usize| *x == item)
     .collect::<Vec<bool>>().par_iter().filter(|x: &&bool| **x).count();
-    return tmp as f32;
+    tmp as f32
+}
+
+/// Days since 1970-01-01 of a civil date (Howard Hinnant's algorithm)
+pub fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 {year - 1} else {year};
+    let era = (if year >= 0 {year} else {year - 399}) / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 {-3} else {9}) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
 }
 
-/// Get the number of sub vectors above a certain length
-pub fn sub_vector_above_this_length(initial_vec: &Vec<Vec<u32>>, length_to_test: usize) -> u32{
-    let caca: usize = initial_vec.par_iter().map(|x| x.len() >= length_to_test).collect::<Vec<bool>>()
-    .par_iter().filter(|x| **x).count();
-    return caca as u32;
+/// Civil date (year, month, day) of days since 1970-01-01, inverse of days_from_civil
+pub fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {month_index + 3} else {month_index - 9};
+    let year = year_of_era + era * 400 + if month <= 2 {1} else {0};
+    (year, month, day)
 }
 
-/// Get sum of subvectors at index x
-pub fn sub_vector_sum_at_index(initial_vec: &Vec<Vec<u32>>, index: usize) -> u32{
-    let caca: Vec<u32> = initial_vec
+/// Phred score to probability of a wrong base call
+pub fn phred_to_error_probability(q: u32) -> f64 {
+    10f64.powf(-(q as f64) / 10.0)
+}
+
+/// Mean quality of a read computed in error probability space
+/// -10 * log10(mean(10^(-Q/10)))
+pub fn mean_read_quality(quality: &[u32]) -> f32 {
+    if quality.is_empty() {
+        return 0.0
+    }
+    let mean_error: f64 = quality.iter().map(|q| phred_to_error_probability(*q)).sum::<f64>() / quality.len() as f64;
+    (-10.0 * mean_error.log10()) as f32
+}
+
+/// Value at a given fraction (0.0 - 1.0) of a histogram where the index is the value
+/// Linear interpolation between the two closest ranks
+pub fn histogram_quantile(histogram: &[u64], fraction: f64) -> f64 {
+    let total: u64 = histogram.iter().sum();
+    if total == 0 {
+        return 0.0
+    }
+    let position = fraction * (total - 1) as f64;
+    let lower_rank = position.floor() as u64;
+    let upper_rank = position.ceil() as u64;
+
+    // Walk the cumulative counts once to find both ranks
+    let mut lower_value: Option<usize> = None;
+    let mut upper_value: usize = 0;
+    let mut cumulative: u64 = 0;
+    for (value, count) in histogram.iter().enumerate() {
+        cumulative += count;
+        if lower_value.is_none() && cumulative > lower_rank {
+            lower_value = Some(value);
+        }
+        if cumulative > upper_rank {
+            upper_value = value;
+            break;
+        }
+    }
+    let lower_value = lower_value.unwrap_or(upper_value) as f64;
+    lower_value + (upper_value as f64 - lower_value) * (position - lower_rank as f64)
+}
+
+/// Value at a given fraction (0.0 - 1.0) of an already sorted vector
+/// Same interpolation as histogram_quantile
+pub fn sorted_quantile(sorted: &[f32], fraction: f64) -> f32 {
+    if sorted.is_empty() {
+        return 0.0
+    }
+    let position = fraction * (sorted.len() - 1) as f64;
+    let lower = sorted[position.floor() as usize];
+    let upper = sorted[position.ceil() as usize];
+    lower + (upper - lower) * (position - position.floor()) as f32
+}
+
+/// Percentage of the histogram at or above a given value
+pub fn histogram_percent_above(histogram: &[u64], value: usize) -> f32 {
+    let total: u64 = histogram.iter().sum();
+    if total == 0 {
+        return 0.0
+    }
+    let above: u64 = histogram.iter().skip(value).sum();
+    (above as f64 / total as f64 * 100.0) as f32
+}
+
+/// Nx of a length histogram (index is the length)
+/// returns the length such that reads at least that long hold fraction of all bases
+/// and the number of reads needed to get there (Lx)
+pub fn histogram_nx(histogram: &[u64], fraction: f64) -> (usize, u64) {
+    let total_bases: u64 = histogram.iter().enumerate().map(|(length, count)| length as u64 * count).sum();
+    let target = total_bases as f64 * fraction;
+    let mut cumulative_bases: u64 = 0;
+    let mut cumulative_reads: u64 = 0;
+    // Longest reads first
+    for (length, count) in histogram.iter().enumerate().rev() {
+        if *count == 0 {continue}
+        for _ in 0..*count {
+            cumulative_bases += length as u64;
+            cumulative_reads += 1;
+            if cumulative_bases as f64 >= target {
+                return (length, cumulative_reads)
+            }
+        }
+    }
+    (0, cumulative_reads)
+}
+
+/// Number of bases per bin so that a read of max_length fits in number_of_bins columns
+pub fn position_bin_size(max_length: usize, number_of_bins: usize) -> usize {
+    max_length.div_ceil(number_of_bins.max(1)).max(1)
+}
+
+/// Mean quality in bins over the first (5') or last (3') window bases of each read
+/// returns (distance from the anchored end, mean quality, reads covering the bin)
+pub fn quality_profile_from_end(qualities: &[Vec<u32>], window: usize, bin_size: usize, from_3_prime: bool) -> Vec<(usize, f32, usize)> {
+    let number_of_bins = window.div_ceil(bin_size);
+    // (sum of qualities, number of bases, number of reads) per bin
+    let (sums, bases, reads) = qualities
+    .par_iter()
+    .fold(|| (vec![0u64; number_of_bins], vec![0u64; number_of_bins], vec![0usize; number_of_bins]), |mut acc, quality| {
+        let covered = quality.len().min(window);
+        for distance in 0..covered {
+            let q = if from_3_prime {quality[quality.len() - 1 - distance]} else {quality[distance]};
+            acc.0[distance / bin_size] += q as u64;
+            acc.1[distance / bin_size] += 1;
+        }
+        // A read counts for a bin if it reaches the start of the bin
+        for bin in 0..covered.div_ceil(bin_size) {acc.2[bin] += 1};
+        acc
+    })
+    .reduce(|| (vec![0u64; number_of_bins], vec![0u64; number_of_bins], vec![0usize; number_of_bins]), |mut a, b| {
+        for bin in 0..number_of_bins {
+            a.0[bin] += b.0[bin];
+            a.1[bin] += b.1[bin];
+            a.2[bin] += b.2[bin];
+        }
+        a
+    });
+
+    (0..number_of_bins)
+    .filter(|bin| bases[*bin] > 0)
+    .map(|bin| (bin * bin_size, sums[bin] as f32 / bases[bin] as f32, reads[bin]))
+    .collect()
+}
+
+/// Mean quality at every step-th position in one pass over the reads
+/// returns (position from 1, mean quality, reads covering the position)
+pub fn position_qualities(qualities: &[Vec<u32>], step: usize) -> Vec<(usize, f32, usize)> {
+    let number_of_positions = qualities.iter().map(|x| x.len()).max().unwrap_or(0).div_ceil(step);
+    // (sum of qualities, number of reads) per position
+    let (sums, reads) = qualities
     .par_iter()
-    .map(|x| x.get(index).unwrap_or(&0).to_owned())
-    .collect();
-    let sumsum: u32 = caca.par_iter().sum();
-    return sumsum
+    .fold(|| (vec![0u64; number_of_positions], vec![0usize; number_of_positions]), |mut acc, quality| {
+        for (i, q) in quality.iter().step_by(step).enumerate() {
+            acc.0[i] += *q as u64;
+            acc.1[i] += 1;
+        }
+        acc
+    })
+    .reduce(|| (vec![0u64; number_of_positions], vec![0usize; number_of_positions]), |mut a, b| {
+        for i in 0..number_of_positions {
+            a.0[i] += b.0[i];
+            a.1[i] += b.1[i];
+        }
+        a
+    });
+
+    (0..number_of_positions)
+    .filter(|i| reads[*i] > 0)
+    .map(|i| (i * step + 1, sums[i] as f32 / reads[i] as f32, reads[i]))
+    .collect()
 }
 
-/// Write reports when in multi mode
-pub fn write_reports(input: Vec<FqObject>) -> () {
-    let mut file = File::create("rsFQC.summary.txt").unwrap();
-    // file.write_all(b"Hello, world!").unwrap();
-    file.write_all(b"File\tMinimum Length\tMedian Length\tAverage Length\tMaximum Lemgth\tMinimum Quality\tMedian Quality\tAverage Quality\tMaximum Quality\tDuplication Level\n").unwrap();
-    for fq in input{
-        let formated_line = format!("{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
-            fq.filepath,fq.len_min, fq.len_median, fq.len_mean, fq.len_max,
-            fq.qual_min, fq.qual_median, fq.qual_mean, fq.qual_max, 
-            fq.duplication_levels );   
-        file.write_all(formated_line.as_bytes()).unwrap();
+/// Number of reads for each rounded mean read quality
+pub fn read_quality_histogram(read_mean_qualities: &[f32]) -> Vec<(u32, usize)> {
+    let mut histogram: Vec<(u32, usize)> = Vec::new();
+    let mut rounded: Vec<u32> = read_mean_qualities.iter().map(|x| x.round() as u32).collect();
+    rounded.sort_unstable();
+    for quality in rounded {
+        match histogram.last_mut() {
+            Some((q, count)) if *q == quality => *count += 1,
+            _ => histogram.push((quality, 1)),
+        }
     }
+    histogram
+}
+
+/// Threads to use - 0 means all CPUs
+pub fn thread_count(threads: usize) -> usize {
+    if threads > 0 {threads} else {std::thread::available_parallelism().map(|x| x.get()).unwrap_or(1)}
+}
+
+/// Process files in multi mode: up to `threads` files at once, the threads split evenly between them
+/// Each file runs in its own rayon pool so per-read work stays within its share. Input order is kept
+pub fn process_files(files: Vec<FqObject>, threads: usize) -> Vec<FqObject> {
+    let jobs = threads.min(files.len()).max(1);
+    let threads_per_file = (threads / jobs).max(1);
+    let next = AtomicUsize::new(0);
+    let slots: Vec<Mutex<Option<FqObject>>> = files.into_iter().map(|x| Mutex::new(Some(x))).collect();
+    std::thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| {
+                let pool = rayon::ThreadPoolBuilder::new().num_threads(threads_per_file).build().unwrap();
+                while let Some(slot) = slots.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let mut fq = slot.lock().unwrap().take().unwrap();
+                    pool.install(|| fq.process_multi());
+                    *slot.lock().unwrap() = Some(fq);
+                }
+            });
+        }
+    });
+    slots.into_iter().filter_map(|x| x.into_inner().unwrap()).collect()
 }
 
 /// Show header
-pub fn header(fq:& mut  FqObject){
+pub fn header(fq: &FqObject){
     println!("~~~~~~~~~~~~~~~~~~~~~~~~~~~");
     println!("~~~~       rsFQC       ~~~~");
     println!("~~~~~~~~~~~~~~~~~~~~~~~~~~~");
     println!("Sampling the first {} records", pretty_print_int(&fq.number_of_records_used));
     println!("of file");
     println!("{}",&fq.filepath);
+    // Illumina filter flags and controls
+    if fq.reads_filtered + fq.reads_passing_filter > 0 {
+        let total = (fq.reads_filtered + fq.reads_passing_filter) as f32;
+        println!("Passing filter (N): {} ({:.2}%)", pretty_print_int(&fq.reads_passing_filter), fq.reads_passing_filter as f32 / total * 100.0);
+        println!("Filtered (Y): {} ({:.2}%)", pretty_print_int(&fq.reads_filtered), fq.reads_filtered as f32 / total * 100.0);
+        if fq.exclude_filtered && fq.reads_filtered > 0 {println!("Filtered reads are excluded from all metrics")};
+    }
+    for (control, count) in fq.control_counts.iter().filter(|(control, _)| *control != 0) {
+        println!("Control reads ({}): {}", control, pretty_print_int(count));
+    }
 }
 
 /// print separator
 pub fn sep(){
     println!("-----------------------------------------------------")
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quality_line_keeps_base_order() {
+        let line: String = (33u8..127).map(|x| x as char).collect();
+        assert_eq!(quality_vector_from_line(line), (0..94).collect::<Vec<u32>>());
+        // Below '!' is clamped instead of underflowing
+        assert_eq!(quality_vector_from_line("\x1f!I".to_string()), vec![0, 0, 40]);
+    }
+
+    #[test]
+    fn civil_dates_round_trip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(days_from_civil(2024, 2, 29)), (2024, 2, 29));
+        for days in [-800_000, -1, 11_016, 19_782, 60_000] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn quantiles_interpolate_between_ranks() {
+        // 1, 2, 3, 4
+        let histogram = [0, 1, 1, 1, 1];
+        assert_eq!(histogram_quantile(&histogram, 0.0), 1.0);
+        assert_eq!(histogram_quantile(&histogram, 0.5), 2.5);
+        assert_eq!(histogram_quantile(&histogram, 1.0), 4.0);
+        assert_eq!(histogram_quantile(&[0, 0, 3], 0.5), 2.0);
+        assert_eq!(histogram_quantile(&[], 0.5), 0.0);
+        assert_eq!(sorted_quantile(&[1.0, 2.0, 3.0, 4.0], 0.5), 2.5);
+        assert_eq!(sorted_quantile(&[], 0.5), 0.0);
+    }
+
+    #[test]
+    fn percent_above_counts_the_value_itself() {
+        assert_eq!(histogram_percent_above(&[0, 0, 1, 1], 3), 50.0);
+        assert_eq!(histogram_percent_above(&[0, 0, 1, 1], 2), 100.0);
+        assert_eq!(histogram_percent_above(&[], 30), 0.0);
+    }
+
+    #[test]
+    fn mean_read_quality_in_error_space() {
+        assert!((mean_read_quality(&[30, 30]) - 30.0).abs() < 1e-4);
+        // (0.1 + 0.001) / 2 -> Q12.97, not the arithmetic mean Q20
+        assert!((mean_read_quality(&[10, 30]) - 12.967).abs() < 1e-3);
+        assert_eq!(mean_read_quality(&[]), 0.0);
+    }
+
+    #[test]
+    fn nx_walks_from_the_longest_reads() {
+        // Reads of 100, 100 and 50 bases - 250 bases
+        let mut histogram = vec![0; 101];
+        histogram[100] = 2;
+        histogram[50] = 1;
+        assert_eq!(histogram_nx(&histogram, 0.5), (100, 2));
+        assert_eq!(histogram_nx(&histogram, 0.9), (50, 3));
+        assert_eq!(histogram_nx(&[], 0.5), (0, 0));
+    }
+
+    #[test]
+    fn shared_sample_names_get_their_directories() {
+        let samples = |paths: &[&str]| -> Vec<String> {
+            let mut input: Vec<FqObject> = paths.iter().map(|x| FqObject::new(x.to_string(), 10)).collect();
+            unique_sample_names(&mut input);
+            input.into_iter().map(|x| x.sample).collect()
+        };
+        assert_eq!(samples(&["run1/S1.fq.gz", "run2/S1.fastq.gz", "S2.fq"]), vec!["run1_S1", "run2_S1", "S2"]);
+        assert_eq!(samples(&["/data/a/lane1/S1.fq.gz", "/data/b/lane1/S1.fq.gz"]), vec!["a_lane1_S1", "b_lane1_S1"]);
+        assert_eq!(samples(&["S1.fq.gz", "run2/S1.fq.gz"]), vec!["S1", "run2_S1"]);
+        assert_eq!(samples(&["S1.fq.gz", "./S1.fq.gz"]), vec!["S1_1", "S1_2"]);
+    }
+
+    #[test]
+    fn position_qualities_follow_read_coverage() {
+        let qualities = vec![vec![30, 20, 10], vec![40, 20], vec![]];
+        assert_eq!(position_qualities(&qualities, 1), vec![(1, 35.0, 2), (2, 20.0, 2), (3, 10.0, 1)]);
+        assert_eq!(position_qualities(&qualities, 2), vec![(1, 35.0, 2), (3, 10.0, 1)]);
+        assert!(position_qualities(&[], 1).is_empty());
+    }
+}
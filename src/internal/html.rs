@@ -0,0 +1,204 @@
+use std::fmt::Write;
+use super::fastq::FqObject;
+use super::func::pretty_print_int;
+use super::plot::{Figure, figures, figure_to_svg, escape};
+use super::verdict::{Verdict, verdict_name};
+use super::contaminants::format_percent;
+
+// HTML REPORT
+// One self-contained <sample>.rsfqc.html per file - inline SVG charts and CSS, no JavaScript,
+// sections collapse with <details> so the file can be attached to an email and opened offline
+// Multi mode also writes rsFQC.index.html linking every report with its verdicts
+
+pub const INDEX_FILE_NAME: &str = "rsFQC.index.html";
+const FIGURE_WIDTH: u32 = 900;
+const FIGURE_HEIGHT: u32 = 360;
+
+const STYLE: &str = "
+body {font-family: Helvetica, Arial, sans-serif; margin: 2em auto; max-width: 960px; color: #222;}
+h1 {margin-bottom: 0.2em;}
+.path {color: #666; margin-top: 0;}
+details {border: 1px solid #ddd; border-radius: 4px; margin: 0.8em 0; padding: 0.3em 0.8em;}
+summary {font-weight: bold; font-size: 1.1em; cursor: pointer; padding: 0.3em 0;}
+table {border-collapse: collapse; margin: 0.5em 0;}
+th, td {border-bottom: 1px solid #eee; padding: 0.25em 0.8em; text-align: left;}
+th {background: #f5f5f5;}
+.badge {display: inline-block; min-width: 3.5em; text-align: center; border-radius: 3px; color: white; font-size: 0.85em; font-weight: bold; padding: 0.1em 0.3em;}
+.PASS {background: #2e9e44;} .WARN {background: #e6a700;} .FAIL {background: #d62728;} .NA {background: #999;}
+code {font-size: 0.9em;}
+";
+
+/// Colored verdict label
+fn badge(verdict: Option<Verdict>) -> String {
+    let name = verdict_name(verdict);
+    format!(r#"<span class="badge {}">{}</span>"#, name, name)
+}
+
+/// Table with a header row
+fn table(header: &[&str], rows: &[Vec<String>]) -> String {
+    let mut html = String::from("<table><tr>");
+    for cell in header {
+        let _ = write!(html, "<th>{}</th>", escape(cell));
+    }
+    html.push_str("</tr>\n");
+    for row in rows {
+        html.push_str("<tr>");
+        for cell in row {
+            let _ = write!(html, "<td>{}</td>", escape(cell));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+/// Inline SVG of the figures with these ids
+fn figures_html(figures: &[Figure], ids: &[&str]) -> String {
+    figures.iter()
+    .filter(|x| ids.contains(&x.id))
+    .map(|x| format!(r#"<div id="{}">{}</div>"#, x.id, figure_to_svg(x, FIGURE_WIDTH, FIGURE_HEIGHT)))
+    .collect()
+}
+
+/// Collapsible section, open by default
+fn section(title: &str, verdict: Option<Option<Verdict>>, body: &str) -> String {
+    let verdict = verdict.map(|x| format!(" {}", badge(x))).unwrap_or_default();
+    format!("<details open><summary>{}{}</summary>\n{}</details>\n", escape(title), verdict, body)
+}
+
+/// HTML report of a processed file
+pub fn html_report(fq: &FqObject) -> String {
+    let figures = figures(fq);
+    let verdicts = &fq.verdicts;
+    let reads = fq.first_n_quality.len();
+    let mut body = String::new();
+
+    // Verdicts
+    let mut summary = String::from("<table><tr>");
+    for (module, _) in verdicts.list() {
+        let _ = write!(summary, "<th>{}</th>", module);
+    }
+    summary.push_str("</tr><tr>");
+    for (_, verdict) in verdicts.list() {
+        let _ = write!(summary, "<td>{}</td>", badge(verdict));
+    }
+    summary.push_str("</tr></table>\n");
+    body.push_str(&section("Status", Some(Some(verdicts.worst())), &summary));
+
+    // Basic statistics
+    let mut rows: Vec<Vec<String>> = vec![
+        vec!["Platform".to_string(), fq.platform.name().to_string()],
+        vec!["Reads sampled".to_string(), pretty_print_int(&reads)],
+        vec!["Total bases".to_string(), pretty_print_int(&(fq.total_bases as usize))],
+        vec!["Read length (min / median / mean / max)".to_string(), format!("{} / {} / {:.2} / {}", fq.len_min, fq.len_median, fq.len_mean, fq.len_max)],
+        vec!["N50".to_string(), pretty_print_int(&(fq.n50 as usize))],
+        vec!["Mean read quality (median / mean)".to_string(), format!("{:.2} / {:.2}", fq.qual_median, fq.qual_mean)],
+        vec!["% Bases >= Q20 / Q30 / Q40".to_string(), format!("{:.2} / {:.2} / {:.2}", fq.percent_q20, fq.percent_q30, fq.percent_q40)],
+        vec!["Quality binning".to_string(), fq.quality_binning.clone().unwrap_or("Not binned".to_string())],
+        vec!["Quality encoding".to_string(), fq.quality_encoding.to_string()],
+    ];
+    if fq.modules.content {rows.push(vec!["GC content".to_string(), format!("{:.2}%", fq.content.gc_percent)])};
+    body.push_str(&section("Basic statistics", None, &table(&["Metric", "Value"], &rows)));
+
+    // Quality and length
+    body.push_str(&section("Quality", Some(verdicts.quality), &figures_html(&figures, &["quality_per_position", "quality_5_prime", "quality_3_prime", "mean_read_quality", "base_quality"])));
+    let mut length = figures_html(&figures, &["length"]);
+    if fq.platform.is_long_reads() && fq.modules.long_reads {
+        let rows: Vec<Vec<String>> = fq.reads_above_thresholds.iter()
+        .map(|(threshold, reads, bases)| vec![format!(">= {}", pretty_print_int(&(*threshold as usize))), pretty_print_int(&(*reads as usize)), pretty_print_int(&(*bases as usize))])
+        .collect();
+        length.push_str(&table(&["Length", "Reads", "Bases"], &rows));
+    }
+    body.push_str(&section("Length", Some(verdicts.length), &length));
+
+    // Optional modules
+    if fq.modules.duplication {
+        let text = format!("<p>Duplicated reads (first 50 bases): {:.2}%</p>\n{}", 100.0 - fq.duplication_levels, figures_html(&figures, &["duplication"]));
+        body.push_str(&section("Duplication", Some(verdicts.duplication), &text));
+    }
+    if fq.modules.content {
+        let text = format!("<p>GC {:.2}%, N {:.2}%. Largest difference at a position: A/T {:.2}%, G/C {:.2}%. Largest N at a position: {:.2}% ({}).</p>\n{}",
+            fq.content.gc_percent, fq.content.n_percent, fq.content.max_at_difference, fq.content.max_gc_difference, fq.content.max_n_percent,
+            verdict_name(verdicts.n), figures_html(&figures, &["content"]));
+        body.push_str(&section("Content", Some(verdicts.content), &text));
+    }
+    if fq.modules.adapters {
+        let rows: Vec<Vec<String>> = fq.adapters.adapters.iter().map(|(name, percent)| vec![name.clone(), format!("{:.2}", percent)]).collect();
+        body.push_str(&section("Adapters", Some(verdicts.adapters), &table(&["Adapter", "% Reads"], &rows)));
+    }
+    if fq.modules.contaminants {
+        let mut rows: Vec<Vec<String>> = vec![
+            vec!["PhiX spike-in".to_string(), format_percent(fq.contaminants.phix_percent)],
+            vec!["rRNA".to_string(), format_percent(fq.contaminants.rrna_percent)],
+        ];
+        rows.extend(fq.contaminants.custom.iter().map(|(name, percent)| vec![name.clone(), format!("{:.2}", percent)]));
+        body.push_str(&section("Contaminants", None, &table(&["Reference", "% Reads"], &rows)));
+    }
+    if fq.modules.complexity {
+        let report = &fq.complexity;
+        let mut text = format!("<p>Low complexity reads: {} ({:.2}%). DUST mean {:.2}, median {:.2}. Entropy mean {:.3}, median {:.3}.</p>\n{}",
+            pretty_print_int(&report.low_complexity_reads), report.low_complexity_percent, report.dust_mean, report.dust_median,
+            report.entropy_mean, report.entropy_median, figures_html(&figures, &["complexity"]));
+        if !report.top_low_complexity.is_empty() {
+            let rows: Vec<Vec<String>> = report.top_low_complexity.iter().map(|(sequence, count)| vec![pretty_print_int(count), sequence[..sequence.len().min(50)].to_string()]).collect();
+            text.push_str(&table(&["Reads", "Sequence (first 50 bases)"], &rows));
+        }
+        body.push_str(&section("Complexity", None, &text));
+    }
+
+    // Platform specific
+    if !fq.lane_deviations.is_empty() {
+        let lanes: Vec<Vec<String>> = fq.lane_deviations.iter().map(|x| vec![x.lane.to_string(), x.tiles.to_string(), pretty_print_int(&x.reads), format!("{:.2}", x.mean_quality), format!("{:+.2}", x.deviation)]).collect();
+        let tiles: Vec<Vec<String>> = fq.tile_deviations.iter().map(|x| vec![x.lane.to_string(), x.tile.to_string(), pretty_print_int(&x.reads), format!("{:+.2}", x.mean_deviation), format!("{:+.2}", x.worst_deviation)]).collect();
+        let text = format!("{}{}", table(&["Lane", "Tiles", "Reads", "Mean Q", "Deviation"], &lanes), table(&["Lane", "Tile", "Reads", "Mean deviation", "Worst deviation"], &tiles));
+        body.push_str(&section("Illumina lanes and tiles", None, &text));
+    }
+    if fq.index_report.reads_with_index > 0 {
+        let rows: Vec<Vec<String>> = fq.index_report.top_indices.iter().map(|(index, count)| vec![index.clone(), pretty_print_int(count), format!("{:.2}", *count as f32 / fq.index_report.reads_with_index as f32 * 100.0)]).collect();
+        body.push_str(&section("Indices", None, &table(&["Index", "Reads", "%"], &rows)));
+    }
+    if fq.nanopore_report.reads_with_header > 0 {
+        let report = &fq.nanopore_report;
+        let mut rows: Vec<Vec<String>> = vec![
+            vec!["Active channels".to_string(), report.active_channels.to_string()],
+            vec!["Run duration (hours)".to_string(), format!("{:.2}", report.run_hours)],
+        ];
+        rows.extend(report.run_ids.iter().map(|(id, count)| vec![format!("Run {}", id), pretty_print_int(count)]));
+        rows.extend(report.basecall_models.iter().map(|(model, count)| vec![format!("Basecall model {}", model), pretty_print_int(count)]));
+        body.push_str(&section("Nanopore run", None, &table(&["", "Reads"], &rows)));
+    }
+
+    let sample = &fq.sample;
+    format!("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>rsFQC - {title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>rsFQC - {title}</h1>\n<p class=\"path\"><code>{path}</code> - rsFQC {version}</p>\n{body}</body>\n</html>\n",
+        title = escape(sample), path = escape(&fq.filepath), version = env!("CARGO_PKG_VERSION"))
+}
+
+/// Index page linking the reports of all files with their verdicts
+pub fn html_index(input: &[FqObject]) -> String {
+    let mut rows = String::new();
+    for fq in input.iter().filter(|x| x.processed) {
+        let sample = &fq.sample;
+        let _ = write!(rows, r#"<tr><td><a href="{}.rsfqc.html">{}</a></td><td>{}</td><td>{}</td><td>{:.2}</td>"#,
+            escape(sample), escape(sample), fq.platform.name(), pretty_print_int(&fq.first_n_quality.len()), fq.qual_median);
+        for (_, verdict) in fq.verdicts.list() {
+            let _ = write!(rows, "<td>{}</td>", badge(verdict));
+        }
+        rows.push_str("</tr>\n");
+    }
+    let modules: String = input.first().map(|fq| fq.verdicts.list().iter().map(|(module, _)| format!("<th>{}</th>", module)).collect()).unwrap_or_default();
+    format!("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>rsFQC - {count} files</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>rsFQC - {count} files</h1>\n<table>\n<tr><th>Sample</th><th>Platform</th><th>Reads sampled</th><th>Median Q</th>{modules}</tr>\n{rows}</table>\n</body>\n</html>\n",
+        count = input.iter().filter(|x| x.processed).count())
+}
+
+/// Write <sample>.rsfqc.html in the working directory, returns the path written
+pub fn write_html_report(fq: &FqObject) -> Result<String, String> {
+    let path = format!("{}.rsfqc.html", fq.sample);
+    std::fs::write(&path, html_report(fq)).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Write rsFQC.index.html in the working directory
+pub fn write_html_index(input: &[FqObject]) -> Result<String, String> {
+    std::fs::write(INDEX_FILE_NAME, html_index(input)).map_err(|e| e.to_string())?;
+    Ok(INDEX_FILE_NAME.to_string())
+}
@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+
+// CASAVA 1.8+ READ NAME
+// @instrument:run:flowcell:lane:tile:x:y read:filtered:control:index
+// @M00995:23:000000000-KWBNC:1:1101:14389:1740 1:N:0:15
+// bcl-convert can add the UMI as an 8th field: @...:14389:1740:ACGTACGT 1:N:0:15
+
+#[derive(Debug,Clone)]
+pub struct IlluminaHeader {
+    pub instrument: String,
+    pub run: String,
+    pub flowcell: String,
+    pub lane: u32,
+    pub tile: u32,
+    pub umi: Option<String>,
+    // Comment part - can be missing (SRA dumps...)
+    pub read: Option<u32>,
+    pub is_filtered: Option<bool>,
+    pub control: Option<u32>,
+    pub index: Option<String>,
+}
+
+/// Quality deviation of a single tile from the mean of all tiles
+#[derive(Debug,Clone,Serialize)]
+pub struct TileDeviation {
+    pub lane: u32,
+    pub tile: u32,
+    pub reads: usize,
+    pub mean_deviation: f32,
+    pub worst_deviation: f32,
+    pub deviations: Vec<f32>, // one per position bin
+}
+
+/// Quality of a lane compared to the mean of all lanes
+#[derive(Debug,Clone,Serialize)]
+pub struct LaneDeviation {
+    pub lane: u32,
+    pub reads: usize,
+    pub tiles: usize,
+    pub mean_quality: f32,
+    pub deviation: f32,
+}
+
+/// Sum of qualities and number of bases in each position bin
+#[derive(Debug,Clone)]
+struct PositionSums {
+    reads: usize,
+    sums: Vec<u64>,
+    counts: Vec<u64>,
+}
+
+impl PositionSums {
+    fn new(number_of_bins: usize) -> Self {
+        Self {reads: 0, sums: vec![0; number_of_bins], counts: vec![0; number_of_bins]}
+    }
+
+    fn mean(&self, bin: usize) -> f32 {
+        if self.counts[bin] == 0 {0.0} else {self.sums[bin] as f32 / self.counts[bin] as f32}
+    }
+}
+
+/// Parse a Casava 1.8+ read name, returns None if the header is not in that format
+pub fn parse_illumina_header(line: &str) -> Option<IlluminaHeader> {
+    let line = line.strip_prefix('@').unwrap_or(line);
+    let mut parts = line.splitn(2, char::is_whitespace);
+    let name: Vec<&str> = parts.next()?.split(':').collect();
+    // x:y coordinates are not kept but must be there
+    if !(7..=8).contains(&name.len()) || name[5].parse::<u32>().is_err() || name[6].parse::<u32>().is_err() {
+        return None
+    }
+
+    let mut header = IlluminaHeader {
+        instrument: name[0].to_string(),
+        run: name[1].to_string(),
+        flowcell: name[2].to_string(),
+        lane: name[3].parse().ok()?,
+        tile: name[4].parse().ok()?,
+        umi: name.get(7).map(|x| x.to_string()),
+        read: None,
+        is_filtered: None,
+        control: None,
+        index: None,
+    };
+
+    // read:filtered:control:index
+    if let Some(comment) = parts.next() {
+        let comment: Vec<&str> = comment.trim().splitn(4, ':').collect();
+        if comment.len() == 4 {
+            header.read = comment[0].parse().ok();
+            header.is_filtered = match comment[1] {"Y" => Some(true), "N" => Some(false), _ => None};
+            header.control = comment[2].parse().ok();
+            // Index can be followed by more comments
+            header.index = comment[3].split_whitespace().next().map(|x| x.to_string());
+        }
+    }
+
+    Some(header)
+}
+
+/// Per tile deviation of the mean quality at each position bin from the mean of all tiles
+/// Headers and qualities must be in the same order
+pub fn tile_deviations(headers: &[Option<IlluminaHeader>], qualities: &[Vec<u32>], bin_size: usize, number_of_bins: usize) -> Vec<TileDeviation> {
+    let mut all_tiles = PositionSums::new(number_of_bins);
+    let mut per_tile: BTreeMap<(u32, u32), PositionSums> = BTreeMap::new();
+
+    for (header, quality) in headers.iter().zip(qualities) {
+        let Some(header) = header else {continue};
+        let tile = per_tile.entry((header.lane, header.tile)).or_insert_with(|| PositionSums::new(number_of_bins));
+        tile.reads += 1;
+        for (position, q) in quality.iter().enumerate() {
+            let bin = (position / bin_size).min(number_of_bins - 1);
+            all_tiles.sums[bin] += *q as u64;
+            all_tiles.counts[bin] += 1;
+            tile.sums[bin] += *q as u64;
+            tile.counts[bin] += 1;
+        }
+    }
+
+    per_tile.into_iter().map(|((lane, tile), sums)| {
+        let deviations: Vec<f32> = (0..number_of_bins)
+        .map(|bin| if sums.counts[bin] == 0 {0.0} else {sums.mean(bin) - all_tiles.mean(bin)})
+        .collect();
+        let covered: Vec<f32> = (0..number_of_bins).filter(|bin| sums.counts[*bin] > 0).map(|bin| deviations[bin]).collect();
+        let mean_deviation = if covered.is_empty() {0.0} else {covered.iter().sum::<f32>() / covered.len() as f32};
+        let worst_deviation = if covered.is_empty() {0.0} else {covered.iter().cloned().fold(f32::INFINITY, f32::min)};
+        TileDeviation {lane, tile, reads: sums.reads, mean_deviation, worst_deviation, deviations}
+    }).collect()
+}
+
+/// Mean base quality of each lane and its deviation from the mean of all lanes
+pub fn lane_deviations(headers: &[Option<IlluminaHeader>], qualities: &[Vec<u32>], tiles: &[TileDeviation]) -> Vec<LaneDeviation> {
+    // lane -> (reads, sum of qualities, number of bases)
+    let mut per_lane: BTreeMap<u32, (usize, u64, u64)> = BTreeMap::new();
+    let mut total: (u64, u64) = (0, 0);
+    for (header, quality) in headers.iter().zip(qualities) {
+        let Some(header) = header else {continue};
+        let sum: u64 = quality.iter().map(|q| *q as u64).sum();
+        let lane = per_lane.entry(header.lane).or_insert((0, 0, 0));
+        lane.0 += 1;
+        lane.1 += sum;
+        lane.2 += quality.len() as u64;
+        total.0 += sum;
+        total.1 += quality.len() as u64;
+    }
+    let overall_mean = if total.1 == 0 {0.0} else {total.0 as f32 / total.1 as f32};
+
+    per_lane.into_iter().map(|(lane, (reads, sum, bases))| {
+        let mean_quality = if bases == 0 {0.0} else {sum as f32 / bases as f32};
+        LaneDeviation {
+            lane,
+            reads,
+            tiles: tiles.iter().filter(|x| x.lane == lane).count(),
+            mean_quality,
+            deviation: mean_quality - overall_mean,
+        }
+    }).collect()
+}
+
+/// Shade character for a quality deviation - only drops in quality are shaded
+pub fn deviation_to_shade(deviation: f32) -> char {
+    if deviation > -1.0 {' '}
+    else if deviation > -2.0 {'░'}
+    else if deviation > -3.0 {'▒'}
+    else if deviation > -5.0 {'▓'}
+    else {'█'}
+}
+
+/// Display the tile x position heatmap
+pub fn tile_heatmap(tiles: &[TileDeviation], bin_size: usize) {
+    let number_of_bins = tiles.first().map(|x| x.deviations.len()).unwrap_or(0);
+    println!("Rows = lane:tile, columns = position ({} bp per column)", bin_size);
+    println!("Legend: ' ' > -1  '░' -1 to -2  '▒' -2 to -3  '▓' -3 to -5  '█' < -5 (Q below mean of all tiles)");
+    println!("{:>10} +{}+", "", "-".repeat(number_of_bins));
+    for tile in tiles {
+        let row: String = tile.deviations.iter().map(|x| deviation_to_shade(*x)).collect();
+        println!("{:>10} |{}|", format!("{}:{}", tile.lane, tile.tile), row);
+    }
+    println!("{:>10} +{}+", "", "-".repeat(number_of_bins));
+    println!("{:>10}  1{:>width$}", "", number_of_bins * bin_size, width = number_of_bins.saturating_sub(1));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_casava_read_names() {
+        let header = parse_illumina_header("@M00995:23:000000000-KWBNC:1:1101:14389:1740 1:N:0:15").unwrap();
+        assert_eq!(header.instrument, "M00995");
+        assert_eq!(header.flowcell, "000000000-KWBNC");
+        assert_eq!((header.lane, header.tile), (1, 1101));
+        assert_eq!(header.read, Some(1));
+        assert_eq!(header.is_filtered, Some(false));
+        assert_eq!(header.control, Some(0));
+        assert_eq!(header.index.as_deref(), Some("15"));
+        assert!(header.umi.is_none());
+    }
+
+    #[test]
+    fn parses_umi_and_missing_comment() {
+        let header = parse_illumina_header("@A01:5:HXXXX:2:2204:100:200:ACGTACGT 2:Y:0:ACGT+TTGA extra").unwrap();
+        assert_eq!(header.umi.as_deref(), Some("ACGTACGT"));
+        assert_eq!(header.is_filtered, Some(true));
+        assert_eq!(header.index.as_deref(), Some("ACGT+TTGA"));
+        let header = parse_illumina_header("@A01:5:HXXXX:2:2204:100:200").unwrap();
+        assert_eq!(header.read, None);
+    }
+
+    #[test]
+    fn rejects_other_read_names() {
+        assert!(parse_illumina_header("@SRR001666.1 071112_SLXA-EAS1_s_7:5:1:817:345 length=36").is_none());
+        assert!(parse_illumina_header("@V300012345L1C001R0010000001/1").is_none());
+        assert!(parse_illumina_header("@A01:5:HXXXX:2:2204:x:200 1:N:0:1").is_none());
+    }
+}
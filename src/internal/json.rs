@@ -0,0 +1,206 @@
+use serde::Serialize;
+use super::fastq::FqObject;
+use super::func::read_quality_histogram;
+use super::verdict::{Thresholds, Verdicts};
+use super::complexity::ComplexityReport;
+use super::contaminants::ContaminantReport;
+use super::content::ContentReport;
+use super::adapters::AdapterReport;
+use super::illumina::{TileDeviation, LaneDeviation};
+use super::barcodes::IndexReport;
+use super::nanopore::NanoporeReport;
+
+// JSON REPORT
+// One <sample>.rsfqc.json per file with every metric, histogram and per-position series
+// Bump the major version when a field is removed or changes meaning, the minor version when fields are added
+// Pairs are written as arrays, e.g. length histogram [[length, reads], ...]
+
+pub const JSON_SCHEMA_VERSION: &str = "1.0";
+
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    schema_version: &'static str,
+    tool: &'static str,
+    tool_version: &'static str,
+    file: JsonFile<'a>,
+    platform: JsonPlatform,
+    verdicts: &'a Verdicts,
+    thresholds: &'a Thresholds,
+    quality: JsonQuality<'a>,
+    length: JsonLength<'a>,
+    duplication: Option<JsonDuplication<'a>>,
+    complexity: Option<&'a ComplexityReport>,
+    contaminants: Option<&'a ContaminantReport>,
+    content: Option<&'a ContentReport>,
+    adapters: Option<&'a AdapterReport>,
+    illumina: Option<JsonIllumina<'a>>,
+    nanopore: Option<&'a NanoporeReport>,
+}
+
+#[derive(Serialize)]
+struct JsonFile<'a> {
+    path: &'a str,
+    sample: String,
+    gzipped: bool,
+    records_sampled: usize,
+}
+
+#[derive(Serialize)]
+struct JsonPlatform {
+    name: &'static str,
+    long_reads: bool,
+    set_from_command_line: bool,
+}
+
+#[derive(Serialize)]
+struct JsonQuality<'a> {
+    read_mean_min: f32,
+    read_mean_q1: f32,
+    read_mean_median: f32,
+    read_mean_mean: f32,
+    read_mean_q3: f32,
+    read_mean_max: f32,
+    percent_bases_q20: f32,
+    percent_bases_q30: f32,
+    percent_bases_q40: f32,
+    base_quality_histogram: Vec<(usize, u64)>, // phred score, bases
+    read_mean_quality_histogram: Vec<(u32, usize)>, // rounded mean read quality, reads
+    alphabet: &'a [(u32, u64)],
+    binning: &'a Option<String>,
+    encoding: &'a str,
+    per_position: Vec<JsonPosition>,
+    five_prime_profile: Vec<JsonPosition>, // long reads only, position = distance to the 5' end
+    three_prime_profile: Vec<JsonPosition>, // long reads only, position = distance to the 3' end
+}
+
+#[derive(Serialize)]
+struct JsonPosition {
+    position: usize,
+    mean_quality: f32,
+    reads: usize,
+}
+
+#[derive(Serialize)]
+struct JsonLength<'a> {
+    min: u32,
+    q1: f32,
+    median: f32,
+    mean: f32,
+    q3: f32,
+    max: u32,
+    histogram: Vec<(usize, u64)>, // length, reads
+    total_bases: u64,
+    n10: u32,
+    n50: u32,
+    n90: u32,
+    l50: u64,
+    reads_above_thresholds: &'a [(u32, u64, u64)], // length, reads, bases
+}
+
+#[derive(Serialize)]
+struct JsonDuplication<'a> {
+    percent_duplicated: f32,
+    histogram: &'a [(usize, usize)], // times seen (10 = 10 or more), sequences
+}
+
+#[derive(Serialize)]
+struct JsonIllumina<'a> {
+    reads_passing_filter: usize,
+    reads_filtered: usize,
+    control_counts: &'a [(u32, usize)],
+    tile_bin_size: usize,
+    lanes: &'a [LaneDeviation],
+    tiles: &'a [TileDeviation],
+    indices: &'a IndexReport,
+}
+
+/// Non zero entries of a histogram as (index, count)
+fn sparse<T: Copy + Default + PartialEq>(histogram: &[T]) -> Vec<(usize, T)> {
+    histogram.iter().enumerate().filter(|(_, x)| **x != T::default()).map(|(i, x)| (i, *x)).collect()
+}
+
+fn positions(series: &[(usize, f32, usize)]) -> Vec<JsonPosition> {
+    series.iter().map(|(position, mean_quality, reads)| JsonPosition {position: *position, mean_quality: *mean_quality, reads: *reads}).collect()
+}
+
+/// JSON report of a processed file
+pub fn json_report(fq: &FqObject) -> String {
+    let report = JsonReport {
+        schema_version: JSON_SCHEMA_VERSION,
+        tool: "rsFQC",
+        tool_version: env!("CARGO_PKG_VERSION"),
+        file: JsonFile {
+            path: &fq.filepath,
+            sample: fq.sample.clone(),
+            gzipped: fq.gzipped,
+            records_sampled: fq.first_n_quality.len(),
+        },
+        platform: JsonPlatform {
+            name: fq.platform.name(),
+            long_reads: fq.platform.is_long_reads(),
+            set_from_command_line: fq.platform_override.is_some(),
+        },
+        verdicts: &fq.verdicts,
+        thresholds: &fq.thresholds,
+        quality: JsonQuality {
+            read_mean_min: fq.qual_min,
+            read_mean_q1: fq.qual_q1,
+            read_mean_median: fq.qual_median,
+            read_mean_mean: fq.qual_mean,
+            read_mean_q3: fq.qual_q3,
+            read_mean_max: fq.qual_max,
+            percent_bases_q20: fq.percent_q20,
+            percent_bases_q30: fq.percent_q30,
+            percent_bases_q40: fq.percent_q40,
+            base_quality_histogram: sparse(&fq.quality_histogram),
+            read_mean_quality_histogram: read_quality_histogram(&fq.read_mean_qualities),
+            alphabet: &fq.quality_alphabet,
+            binning: &fq.quality_binning,
+            encoding: fq.quality_encoding,
+            per_position: positions(&fq.position_qualities),
+            five_prime_profile: positions(&fq.five_prime_profile),
+            three_prime_profile: positions(&fq.three_prime_profile),
+        },
+        length: JsonLength {
+            min: fq.len_min,
+            q1: fq.len_q1,
+            median: fq.len_median,
+            mean: fq.len_mean,
+            q3: fq.len_q3,
+            max: fq.len_max,
+            histogram: sparse(&fq.length_histogram),
+            total_bases: fq.total_bases,
+            n10: fq.n10,
+            n50: fq.n50,
+            n90: fq.n90,
+            l50: fq.l50,
+            reads_above_thresholds: &fq.reads_above_thresholds,
+        },
+        duplication: fq.modules.duplication.then(|| JsonDuplication {
+            percent_duplicated: 100.0 - fq.duplication_levels,
+            histogram: &fq.duplication_histogram,
+        }),
+        complexity: fq.modules.complexity.then_some(&fq.complexity),
+        contaminants: fq.modules.contaminants.then_some(&fq.contaminants),
+        content: fq.modules.content.then_some(&fq.content),
+        adapters: fq.modules.adapters.then_some(&fq.adapters),
+        illumina: (fq.platform.has_illumina_headers() && fq.modules.illumina).then(|| JsonIllumina {
+            reads_passing_filter: fq.reads_passing_filter,
+            reads_filtered: fq.reads_filtered,
+            control_counts: &fq.control_counts,
+            tile_bin_size: fq.tile_bin_size,
+            lanes: &fq.lane_deviations,
+            tiles: &fq.tile_deviations,
+            indices: &fq.index_report,
+        }),
+        nanopore: (fq.nanopore_report.reads_with_header > 0).then_some(&fq.nanopore_report),
+    };
+    serde_json::to_string_pretty(&report).unwrap()
+}
+
+/// Write <sample>.rsfqc.json in the working directory, returns the path written
+pub fn write_json_report(fq: &FqObject) -> Result<String, String> {
+    let path = format!("{}.rsfqc.json", fq.sample);
+    std::fs::write(&path, json_report(fq)).map_err(|e| e.to_string())?;
+    Ok(path)
+}
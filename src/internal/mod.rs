@@ -1,2 +1,25 @@
 pub mod func;
-pub mod fastq;
\ No newline at end of file
+pub mod fastq;
+pub mod illumina;
+pub mod barcodes;
+pub mod nanopore;
+pub mod density;
+pub mod platform;
+pub mod binning;
+pub mod complexity;
+pub mod screen;
+pub mod contaminants;
+pub mod content;
+pub mod adapters;
+pub mod verdict;
+pub mod config;
+pub mod json;
+pub mod plot;
+pub mod html;
+pub mod fastqc;
+pub mod multiqc;
+pub mod summary;
+pub mod outliers;
+pub mod samples;
+pub mod comparison;
+pub mod options;
\ No newline at end of file
@@ -0,0 +1,157 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+use serde_json::{json, Map, Value};
+use super::fastq::FqObject;
+
+// MULTIQC CUSTOM CONTENT
+// *_mqc.tsv / *_mqc.json files picked up by MultiQC for the metrics FastQC does not have
+// rsfqc_general_stats_mqc.tsv: columns added to the General Statistics table
+// rsfqc_metrics_mqc.json: rsFQC section (N50, yield, PolyG, complexity, contaminants, UMIs...)
+// rsfqc_nanopore_mqc.json and rsfqc_nanopore_yield_mqc.json: channel stats and yield over time (ONT only)
+
+pub const GENERAL_STATS_FILE_NAME: &str = "rsfqc_general_stats_mqc.tsv";
+pub const METRICS_FILE_NAME: &str = "rsfqc_metrics_mqc.json";
+pub const NANOPORE_FILE_NAME: &str = "rsfqc_nanopore_mqc.json";
+pub const NANOPORE_YIELD_FILE_NAME: &str = "rsfqc_nanopore_yield_mqc.json";
+
+/// General stats columns - key, title, description, format
+const GENERAL_STATS: [(&str, &str, &str, &str); 5] = [
+    ("n50", "N50", "Read length N50 (bp)", "{:,.0f}"),
+    ("percent_q30", "% Q30", "% of bases with a quality of 30 or more", "{:,.1f}"),
+    ("polyg_percent", "% PolyG", "% of reads with 12 or more G in a row", "{:,.2f}"),
+    ("low_complexity_percent", "% Low Cplx", "% of low complexity reads (DUST)", "{:,.2f}"),
+    ("umi_diversity", "UMI Div.", "Distinct UMIs / reads with a UMI (%)", "{:,.1f}"),
+];
+
+/// % of reads with the PolyG adapter, None if it is not in the adapter list
+fn polyg_percent(fq: &FqObject) -> Option<f32> {
+    if !fq.modules.adapters {
+        return None
+    }
+    fq.adapters.adapters.iter().find(|(name, _)| name == "PolyG").map(|x| x.1)
+}
+
+/// Reads with a UMI in their name and distinct UMIs
+fn umi_counts(fq: &FqObject) -> (usize, usize) {
+    let umis: Vec<&str> = fq.illumina_headers.iter().flatten().filter_map(|x| x.umi.as_deref()).collect();
+    let distinct: HashSet<&str> = umis.iter().cloned().collect();
+    (umis.len(), distinct.len())
+}
+
+fn umi_diversity(fq: &FqObject) -> Option<f32> {
+    let (reads, distinct) = umi_counts(fq);
+    if reads == 0 {None} else {Some(distinct as f32 / reads as f32 * 100.0)}
+}
+
+/// General Statistics columns in MultiQC TSV custom content format
+pub fn general_stats_tsv(input: &[FqObject]) -> String {
+    let mut tsv = String::from("# id: 'rsfqc_general_stats'\n# plot_type: 'generalstats'\n# pconfig:\n");
+    for (key, title, description, format) in GENERAL_STATS {
+        let _ = write!(tsv, "#     - {}:\n#         title: '{}'\n#         description: '{}'\n#         format: '{}'\n", key, title, description, format);
+    }
+    let _ = writeln!(tsv, "Sample\t{}", GENERAL_STATS.map(|x| x.0).join("\t"));
+    let optional = |value: Option<f32>| value.map(|x| format!("{:.2}", x)).unwrap_or_default();
+    for fq in input.iter().filter(|x| x.processed) {
+        let low_complexity = fq.modules.complexity.then_some(fq.complexity.low_complexity_percent);
+        let _ = writeln!(tsv, "{}\t{}\t{:.2}\t{}\t{}\t{}", fq.sample, fq.n50, fq.percent_q30,
+            optional(polyg_percent(fq)), optional(low_complexity), optional(umi_diversity(fq)));
+    }
+    tsv
+}
+
+/// rsFQC metrics table section
+pub fn metrics_json(input: &[FqObject]) -> Value {
+    let mut data = Map::new();
+    for fq in input.iter().filter(|x| x.processed) {
+        let mut row = Map::new();
+        row.insert("platform".to_string(), json!(fq.platform.name()));
+        row.insert("reads_sampled".to_string(), json!(fq.first_n_quality.len()));
+        row.insert("total_bases".to_string(), json!(fq.total_bases));
+        row.insert("n10".to_string(), json!(fq.n10));
+        row.insert("n50".to_string(), json!(fq.n50));
+        row.insert("n90".to_string(), json!(fq.n90));
+        row.insert("l50".to_string(), json!(fq.l50));
+        row.insert("median_read_quality".to_string(), json!(fq.qual_median));
+        row.insert("quality_alphabet_size".to_string(), json!(fq.quality_alphabet.len()));
+        row.insert("quality_binning".to_string(), json!(fq.quality_binning.clone().unwrap_or("No".to_string())));
+        if let Some(polyg) = polyg_percent(fq) {row.insert("polyg_percent".to_string(), json!(polyg));}
+        if fq.modules.complexity {row.insert("low_complexity_percent".to_string(), json!(fq.complexity.low_complexity_percent));}
+        if let Some(phix) = fq.contaminants.phix_percent {row.insert("phix_percent".to_string(), json!(phix));}
+        if let Some(rrna) = fq.contaminants.rrna_percent {row.insert("rrna_percent".to_string(), json!(rrna));}
+        for (name, percent) in &fq.contaminants.custom {
+            row.insert(format!("{}_percent", name), json!(percent));
+        }
+        let (umi_reads, distinct_umis) = umi_counts(fq);
+        if umi_reads > 0 {
+            row.insert("reads_with_umi".to_string(), json!(umi_reads));
+            row.insert("distinct_umis".to_string(), json!(distinct_umis));
+        }
+        row.insert("worst_verdict".to_string(), json!(fq.verdicts.worst().name()));
+        data.insert(fq.sample.clone(), Value::Object(row));
+    }
+    json!({
+        "id": "rsfqc_metrics",
+        "section_name": "rsFQC",
+        "description": "Metrics computed by rsFQC on the sampled reads that FastQC does not report.",
+        "plot_type": "table",
+        "pconfig": {"id": "rsfqc_metrics_table", "title": "rsFQC metrics"},
+        "data": data,
+    })
+}
+
+/// Nanopore channel table and yield over time line graph, None without ONT read names
+pub fn nanopore_json(input: &[FqObject]) -> Option<(Value, Value)> {
+    let nanopore: Vec<&FqObject> = input.iter().filter(|x| x.processed && x.nanopore_report.reads_with_header > 0).collect();
+    if nanopore.is_empty() {
+        return None
+    }
+    let mut table = Map::new();
+    let mut lines = Map::new();
+    for fq in nanopore {
+        let report = &fq.nanopore_report;
+        let channel_bases: u64 = report.channels.iter().map(|x| x.2).sum();
+        table.insert(fq.sample.clone(), json!({
+            "active_channels": report.active_channels,
+            "run_hours": report.run_hours,
+            "reads_per_channel": report.channels.iter().map(|x| x.1).sum::<usize>() as f32 / report.active_channels.max(1) as f32,
+            "bases_per_channel": channel_bases as f32 / report.active_channels.max(1) as f32,
+            "run_ids": report.run_ids.len(),
+            "flow_cells": report.flow_cell_ids.iter().map(|x| x.0.as_str()).collect::<Vec<&str>>().join(", "),
+        }));
+        let points: Map<String, Value> = report.yield_over_time.iter().map(|(hours, bases)| (format!("{:.3}", hours), json!(bases))).collect();
+        lines.insert(fq.sample.clone(), Value::Object(points));
+    }
+    let table = json!({
+        "id": "rsfqc_nanopore",
+        "section_name": "rsFQC: Nanopore channels",
+        "description": "Channel activity of the sampled reads, from the Nanopore read names.",
+        "plot_type": "table",
+        "pconfig": {"id": "rsfqc_nanopore_table", "title": "rsFQC: Nanopore channels"},
+        "data": table,
+    });
+    let lines = json!({
+        "id": "rsfqc_nanopore_yield",
+        "section_name": "rsFQC: Nanopore yield over time",
+        "description": "Cumulative bases of the sampled reads by hours since the first read.",
+        "plot_type": "linegraph",
+        "pconfig": {"id": "rsfqc_nanopore_yield_plot", "title": "rsFQC: Nanopore yield over time", "xlab": "Hours", "ylab": "Bases"},
+        "data": lines,
+    });
+    Some((table, lines))
+}
+
+/// Write the MultiQC custom content files in the working directory, returns the paths written
+pub fn write_multiqc(input: &[FqObject]) -> Result<Vec<String>, String> {
+    let mut files: Vec<(&str, String)> = vec![
+        (GENERAL_STATS_FILE_NAME, general_stats_tsv(input)),
+        (METRICS_FILE_NAME, metrics_json(input).to_string()),
+    ];
+    if let Some((table, lines)) = nanopore_json(input) {
+        files.push((NANOPORE_FILE_NAME, table.to_string()));
+        files.push((NANOPORE_YIELD_FILE_NAME, lines.to_string()));
+    }
+    for (path, contents) in &files {
+        std::fs::write(path, contents).map_err(|e| e.to_string())?;
+    }
+    Ok(files.iter().map(|x| x.0.to_string()).collect())
+}
@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use itertools::Itertools;
+use serde::Serialize;
+use super::func::days_from_civil;
+
+// OXFORD NANOPORE READ NAME
+// @read_id runid=... sampleid=... read=12 ch=245 start_time=2021-03-04T12:34:56Z flow_cell_id=FAP12345 basecall_model_version_id=dna_r9.4.1_450bps_hac
+
+/// Reads started more than this away from the median start time are left out of the yield over time
+/// (a single wrong start_time would otherwise stretch the run to years of 10 minute bins)
+const MAX_RUN_SECONDS: i64 = 7 * 86400;
+
+#[derive(Debug,Clone,Default)]
+pub struct NanoporeHeader {
+    pub run_id: Option<String>,
+    pub channel: Option<u32>,
+    pub start_time: Option<i64>, // seconds since epoch
+    pub flow_cell_id: Option<String>,
+    pub basecall_model: Option<String>,
+}
+
+/// Run summary from the nanopore read names
+#[derive(Debug,Clone,Default,Serialize)]
+pub struct NanoporeReport {
+    pub reads_with_header: usize,
+    pub run_ids: Vec<(String, usize)>,
+    pub flow_cell_ids: Vec<(String, usize)>,
+    pub basecall_models: Vec<(String, usize)>,
+    pub active_channels: usize,
+    pub channels: Vec<(u32, usize, u64)>, // channel, reads, bases
+    pub run_hours: f32,
+    pub yield_over_time: Vec<(f32, u64)>, // hours since first read, cumulative bases
+}
+
+/// Parse an ISO 8601 timestamp (2021-03-04T12:34:56.789+00:00 or ...Z) into seconds since epoch
+pub fn parse_timestamp(timestamp: &str) -> Option<i64> {
+    let (date, time) = timestamp.split_once('T')?;
+    let date: Vec<i64> = date.split('-').map(|x| x.parse().ok()).collect::<Option<Vec<i64>>>()?;
+    if date.len() != 3 {
+        return None
+    }
+
+    // Split the time zone
+    let (time, offset) = if let Some(time) = time.strip_suffix('Z') {
+        (time, 0)
+    } else if let Some(position) = time.rfind(['+', '-']) {
+        let sign = if time[position..].starts_with('-') {-1} else {1};
+        let zone: Vec<i64> = time[position + 1..].split(':').map(|x| x.parse().ok()).collect::<Option<Vec<i64>>>()?;
+        (&time[..position], sign * (zone.first()? * 3600 + zone.get(1).unwrap_or(&0) * 60))
+    } else {
+        (time, 0)
+    };
+
+    let time: Vec<f64> = time.split(':').map(|x| x.parse().ok()).collect::<Option<Vec<f64>>>()?;
+    if time.len() != 3 {
+        return None
+    }
+    let seconds = time[0] as i64 * 3600 + time[1] as i64 * 60 + time[2] as i64;
+    Some(days_from_civil(date[0], date[1], date[2]) * 86400 + seconds - offset)
+}
+
+/// Parse the key=value fields of a nanopore read name, returns None if there is none
+pub fn parse_nanopore_header(line: &str) -> Option<NanoporeHeader> {
+    let mut header = NanoporeHeader::default();
+    let mut found = false;
+    for field in line.split_whitespace().skip(1) {
+        let Some((key, value)) = field.split_once('=') else {continue};
+        match key {
+            "runid" => header.run_id = Some(value.to_string()),
+            "ch" => header.channel = value.parse().ok(),
+            "start_time" => header.start_time = parse_timestamp(value),
+            "flow_cell_id" => header.flow_cell_id = Some(value.to_string()),
+            "basecall_model_version_id" | "model_version_id" => header.basecall_model = Some(value.to_string()),
+            _ => continue,
+        }
+        found = true;
+    }
+    if found {Some(header)} else {None}
+}
+
+/// Count the distinct values, most frequent first
+fn value_counts<'a>(values: impl Iterator<Item = &'a String>) -> Vec<(String, usize)> {
+    values.counts().into_iter()
+    .map(|(x, count)| (x.to_string(), count))
+    .sorted_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)))
+    .collect()
+}
+
+/// Run summary from read names and read lengths (same order)
+pub fn nanopore_report(headers: &[String], lengths: &[usize]) -> NanoporeReport {
+    let parsed: Vec<(NanoporeHeader, usize)> = headers.iter().zip(lengths)
+    .filter_map(|(x, length)| parse_nanopore_header(x).map(|header| (header, *length)))
+    .collect();
+
+    let mut report = NanoporeReport {
+        reads_with_header: parsed.len(),
+        run_ids: value_counts(parsed.iter().filter_map(|(x, _)| x.run_id.as_ref())),
+        flow_cell_ids: value_counts(parsed.iter().filter_map(|(x, _)| x.flow_cell_id.as_ref())),
+        basecall_models: value_counts(parsed.iter().filter_map(|(x, _)| x.basecall_model.as_ref())),
+        ..Default::default()
+    };
+
+    // Reads and bases per channel
+    let mut channels: BTreeMap<u32, (usize, u64)> = BTreeMap::new();
+    for (header, length) in &parsed {
+        let Some(channel) = header.channel else {continue};
+        let entry = channels.entry(channel).or_insert((0, 0));
+        entry.0 += 1;
+        entry.1 += *length as u64;
+    }
+    report.active_channels = channels.len();
+    report.channels = channels.into_iter().map(|(channel, (reads, bases))| (channel, reads, bases)).collect();
+
+    // Cumulative yield over time, binned every 10 minutes
+    let mut timed: Vec<(i64, usize)> = parsed.iter().filter_map(|(x, length)| x.start_time.map(|time| (time, *length))).collect();
+    if let Some(median) = timed.iter().map(|x| x.0).sorted().nth(timed.len() / 2) {
+        timed.retain(|(time, _)| (time - median).abs() <= MAX_RUN_SECONDS);
+    }
+    if let (Some(first), Some(last)) = (timed.iter().map(|x| x.0).min(), timed.iter().map(|x| x.0).max()) {
+        report.run_hours = (last - first) as f32 / 3600.0;
+        let number_of_bins = ((last - first) / 600 + 1) as usize;
+        let mut bases_per_bin: Vec<u64> = vec![0; number_of_bins];
+        for (time, length) in &timed {
+            bases_per_bin[((time - first) / 600) as usize] += *length as u64;
+        }
+        let mut cumulative: u64 = 0;
+        report.yield_over_time = bases_per_bin.iter().enumerate().map(|(bin, bases)| {
+            cumulative += bases;
+            ((bin + 1) as f32 / 6.0, cumulative)
+        }).collect();
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_timestamps_with_time_zones() {
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(parse_timestamp("2021-03-04T12:34:56Z"), Some(1614861296));
+        assert_eq!(parse_timestamp("2021-03-04T12:34:56.789Z"), Some(1614861296));
+        assert_eq!(parse_timestamp("2021-03-04T13:34:56+01:00"), Some(1614861296));
+        assert_eq!(parse_timestamp("2021-03-04T07:34:56-05:00"), Some(1614861296));
+        // Leap day
+        assert_eq!(parse_timestamp("2020-03-01T00:00:00Z").unwrap() - parse_timestamp("2020-02-28T00:00:00Z").unwrap(), 2 * 86400);
+        assert_eq!(parse_timestamp("2021-03-04"), None);
+        assert_eq!(parse_timestamp("2021-03T12:34:56Z"), None);
+    }
+
+    #[test]
+    fn parses_read_name_fields() {
+        let line = "@0b7a1f5e-2f3b-4c55-9d1e-1b2c3d4e5f60 runid=abc123 read=12 ch=245 start_time=2021-03-04T12:34:56Z flow_cell_id=FAP12345 basecall_model_version_id=dna_r9.4.1_450bps_hac";
+        let header = parse_nanopore_header(line).unwrap();
+        assert_eq!(header.run_id.as_deref(), Some("abc123"));
+        assert_eq!(header.channel, Some(245));
+        assert_eq!(header.start_time, Some(1614861296));
+        assert_eq!(header.flow_cell_id.as_deref(), Some("FAP12345"));
+        assert_eq!(header.basecall_model.as_deref(), Some("dna_r9.4.1_450bps_hac"));
+        assert!(parse_nanopore_header("@M00995:23:000000000-KWBNC:1:1101:14389:1740 1:N:0:15").is_none());
+    }
+
+    #[test]
+    fn yield_over_time_ignores_far_off_start_times() {
+        let headers: Vec<String> = ["2021-03-04T12:00:00Z", "2021-03-04T12:30:00Z", "2021-03-04T13:00:00Z", "1999-01-01T00:00:00Z"].iter()
+        .map(|x| format!("@read ch=1 start_time={}", x))
+        .collect();
+        let report = nanopore_report(&headers, &[100, 200, 300, 400]);
+        assert_eq!(report.run_hours, 1.0);
+        assert_eq!(report.yield_over_time.len(), 7);
+        assert_eq!(report.yield_over_time.last().unwrap().1, 600);
+        assert_eq!(report.channels, vec![(1, 4, 1000)]);
+    }
+}
@@ -0,0 +1,183 @@
+// COMMAND LINE
+// rsFQC [options] file1.fq.gz [file2.fq.gz ...]
+// rsFQC screen -r reference.fa [-r reference2.fa ...] file1.fq.gz [file2.fq.gz ...]
+// Anything that is not a flag is treated as a file to process
+use super::platform::Platform;
+
+#[derive(Debug,Clone)]
+pub struct Options {
+    pub files: Vec<String>,
+    pub sample_sheet: Option<String>,
+    pub exclude_filtered: bool,
+    pub platform: Option<Platform>,
+    pub config: Option<String>,
+    pub thresholds: Option<String>,
+    pub verdict_exit: bool,
+    pub json: bool,
+    pub html: bool,
+    pub figures: bool,
+    pub fastqc: bool,
+    pub fastqc_zip: bool,
+    pub multiqc: bool,
+    pub summary_format: Option<String>,
+    pub summary_prefix: Option<String>,
+    pub append: bool,
+    pub threads: Option<usize>,
+    pub group_lanes: bool,
+    pub group_regex: Option<String>,
+    pub group_sheet: Option<String>,
+    // screen subcommand
+    pub screen: bool,
+    pub references: Vec<String>,
+    pub kmer_size: usize,
+    pub scaled: u64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            files: Vec::new(),
+            sample_sheet: None,
+            exclude_filtered: false,
+            platform: None,
+            config: None,
+            thresholds: None,
+            verdict_exit: false,
+            json: false,
+            html: false,
+            figures: false,
+            fastqc: false,
+            fastqc_zip: false,
+            multiqc: false,
+            summary_format: None,
+            summary_prefix: None,
+            append: false,
+            threads: None,
+            group_lanes: false,
+            group_regex: None,
+            group_sheet: None,
+            screen: false,
+            references: Vec::new(),
+            kmer_size: 21,
+            scaled: 8,
+        }
+    }
+}
+
+/// Print usage
+pub fn usage() {
+    println!("Usage: rsFQC [options] <file.fq.gz> [<file2.fq.gz> ...]");
+    println!();
+    println!("Options:");
+    println!("  --sample-sheet <file.csv>   Expected indices (Illumina sample sheet or CSV with index/index2 columns)");
+    println!("  --exclude-filtered          Drop reads flagged as filtered (Y) in Illumina read names from all metrics");
+    println!("  --platform <name>           Skip platform detection: illumina, element, mgi, iontorrent, ont, hifi, clr, unknown");
+    println!("  -t, --threads <n>           Threads used in total (default: all CPUs), several files are processed at once in multi mode");
+    println!("  --config <rsfqc.toml>       Configuration file (default: ./rsfqc.toml, then $XDG_CONFIG_HOME/rsfqc/rsfqc.toml)");
+    println!("  --json                      Also write <sample>.rsfqc.json with every metric for each file");
+    println!("  --html                      Also write <sample>.rsfqc.html (and rsFQC.index.html in multi mode)");
+    println!("  --figures                   Also write every chart as SVG and PNG files in rsfqc_figures/ (see [figures] in rsfqc.toml)");
+    println!("  --fastqc                    Also write <sample>_fastqc/fastqc_data.txt and summary.txt like FastQC (for MultiQC)");
+    println!("  --fastqc-zip                Same as --fastqc and also zip it as <sample>_fastqc.zip");
+    println!("  --multiqc                   Also write rsfqc_*_mqc.tsv / .json MultiQC custom content (N50, PolyG, UMIs, Nanopore...)");
+    println!("  --summary-format <format>   Summary table format: tsv (default), csv, jsonl, markdown");
+    println!("  --summary-prefix <prefix>   Write the summary table to <prefix>.summary.<extension> (default: rsFQC)");
+    println!("  --append                    Add rows to an existing summary table instead of overwriting it");
+    println!("  --group-lanes               Group files into samples by Illumina file names (<sample>_S1_L001_R1_001) in multi mode");
+    println!("  --group-regex <regex>       Group files by a regex on the file name, named groups \"sample\" and \"lane\"");
+    println!("  --group-sheet <file.csv>    Group files with a sheet with file, sample and optional lane columns");
+    println!("  --thresholds <file.toml>    PASS / WARN / FAIL thresholds (see README), missing keys keep their default");
+    println!("  --verdict-exit              Exit with 2 if the worst verdict is WARN and 3 if it is FAIL");
+    println!("  -h, --help                  Show this help");
+    println!();
+    println!("Contamination screen: rsFQC screen -r <reference.fa> [-r <reference2.fa> ...] <file.fq.gz> [...]");
+    println!("  -r, --reference <[name=]file.fa>  Reference FASTA (can be gzipped), name defaults to the file name");
+    println!("  -k, --kmer-size <n>               k-mer size, 1 to 32 (default 21)");
+    println!("  --scaled <n>                      Keep 1 k-mer in n to reduce memory (default 8)");
+}
+
+/// Parse a number or exit
+fn number_value<T: std::str::FromStr>(value: String, flag: &str) -> T {
+    match value.parse() {
+        Ok(number) => number,
+        Err(_) => {
+            println!("{} needs a number, got {}", flag, value);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Get the value following a flag or exit
+fn flag_value(args: &mut impl Iterator<Item = String>, flag: &str) -> String {
+    match args.next() {
+        Some(value) => value,
+        None => {
+            println!("Missing value for {}", flag);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Parse command line arguments (without the executable location)
+pub fn parse_arguments(args: Vec<String>) -> Options {
+    let mut options = Options::default();
+    let mut args = args.into_iter().peekable();
+    if args.peek().map(|x| x == "screen").unwrap_or(false) {
+        options.screen = true;
+        args.next();
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                usage();
+                std::process::exit(0);
+            },
+            "--sample-sheet" => options.sample_sheet = Some(flag_value(&mut args, &arg)),
+            "--exclude-filtered" => options.exclude_filtered = true,
+            "-t" | "--threads" => options.threads = Some(number_value(flag_value(&mut args, &arg), &arg)),
+            "--config" => options.config = Some(flag_value(&mut args, &arg)),
+            "--thresholds" => options.thresholds = Some(flag_value(&mut args, &arg)),
+            "--verdict-exit" => options.verdict_exit = true,
+            "--json" => options.json = true,
+            "--html" => options.html = true,
+            "--figures" => options.figures = true,
+            "--fastqc" => options.fastqc = true,
+            "--multiqc" => options.multiqc = true,
+            "--summary-format" => options.summary_format = Some(flag_value(&mut args, &arg)),
+            "--summary-prefix" => options.summary_prefix = Some(flag_value(&mut args, &arg)),
+            "--append" => options.append = true,
+            "--group-lanes" => options.group_lanes = true,
+            "--group-regex" => options.group_regex = Some(flag_value(&mut args, &arg)),
+            "--group-sheet" => options.group_sheet = Some(flag_value(&mut args, &arg)),
+            "--fastqc-zip" => {
+                options.fastqc = true;
+                options.fastqc_zip = true;
+            },
+            "--platform" => {
+                let value = flag_value(&mut args, &arg);
+                match Platform::from_name(&value) {
+                    Some(platform) => options.platform = Some(platform),
+                    None => {
+                        println!("Unknown platform {}", value);
+                        usage();
+                        std::process::exit(1);
+                    }
+                }
+            },
+            "-r" | "--reference" if options.screen => options.references.push(flag_value(&mut args, &arg)),
+            "-k" | "--kmer-size" if options.screen => options.kmer_size = number_value(flag_value(&mut args, &arg), &arg),
+            "--scaled" if options.screen => options.scaled = number_value(flag_value(&mut args, &arg), &arg),
+            _ if arg.starts_with("--") => {
+                println!("Unknown option {}", arg);
+                usage();
+                std::process::exit(1);
+            },
+            _ => options.files.push(arg),
+        }
+    }
+    if options.screen && (options.kmer_size == 0 || options.kmer_size > 32 || options.scaled == 0) {
+        println!("k-mer size must be between 1 and 32 and scaled at least 1");
+        std::process::exit(1);
+    }
+    options
+}
@@ -0,0 +1,148 @@
+use serde::Serialize;
+use super::fastq::FqObject;
+use super::func::sorted_quantile;
+
+// CROSS-SAMPLE OUTLIERS (multi mode)
+// Each metric is compared to the cohort with a robust z-score: (value - median) / (1.4826 * MAD)
+// Files beyond MAX_Z are flagged - the bad library out of 96 stands out without assuming normal data
+// Reads are sampled, so yield is the file size on disk - only compared when all files have the same compression
+
+pub const MAX_Z: f32 = 3.5;
+/// Fewer files than this and the median / MAD mean nothing
+pub const MIN_FILES: usize = 4;
+/// MAD floor as a fraction of the median, so identical files do not turn tiny differences into outliers
+const MIN_MAD_FRACTION: f32 = 0.01;
+
+#[derive(Debug,Clone,Serialize)]
+pub struct Outlier {
+    pub metric: &'static str,
+    pub value: f32,
+    pub median: f32,
+    pub z: f32,
+}
+
+impl Outlier {
+    pub fn direction(&self) -> &'static str {
+        if self.z < 0.0 {"low"} else {"high"}
+    }
+}
+
+/// Metrics compared across files, None when the module is disabled or the sizes cannot be compared
+fn metrics(fq: &FqObject, same_compression: bool) -> [(&'static str, Option<f32>); 5] {
+    [
+        ("quality", Some(fq.qual_median)),
+        ("length", Some(fq.len_median)),
+        ("gc", fq.modules.content.then_some(fq.content.gc_percent)),
+        ("duplication", fq.modules.duplication.then_some(100.0 - fq.duplication_levels)),
+        ("yield", same_compression.then_some(fq.file_bytes as f32)),
+    ]
+}
+
+/// Median and scaled MAD of some values
+fn median_mad(values: &[f32]) -> (f32, f32) {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable_by(|a, b| a.total_cmp(b));
+    let median = sorted_quantile(&sorted, 0.5);
+    let mut deviations: Vec<f32> = values.iter().map(|x| (x - median).abs()).collect();
+    deviations.sort_unstable_by(|a, b| a.total_cmp(b));
+    let mad = 1.4826 * sorted_quantile(&deviations, 0.5);
+    (median, mad.max(median.abs() * MIN_MAD_FRACTION).max(f32::EPSILON))
+}
+
+/// Flag outliers in every processed file, left as None with fewer than MIN_FILES files
+pub fn flag_outliers(input: &mut [FqObject]) {
+    let processed: Vec<usize> = (0..input.len()).filter(|i| input[*i].processed).collect();
+    if processed.len() < MIN_FILES {
+        return
+    }
+    for i in &processed {
+        input[*i].outliers = Some(Vec::new());
+    }
+    let same_compression = processed.iter().all(|i| input[*i].gzipped == input[processed[0]].gzipped);
+    for metric in 0..metrics(&input[processed[0]], same_compression).len() {
+        let values: Vec<(usize, f32)> = processed.iter().filter_map(|i| metrics(&input[*i], same_compression)[metric].1.map(|x| (*i, x))).collect();
+        if values.len() < MIN_FILES {continue};
+        let (median, mad) = median_mad(&values.iter().map(|x| x.1).collect::<Vec<f32>>());
+        for (i, value) in values {
+            let z = (value - median) / mad;
+            if z.abs() > MAX_Z {
+                let name = metrics(&input[i], same_compression)[metric].0;
+                if let Some(outliers) = input[i].outliers.as_mut() {outliers.push(Outlier {metric: name, value, median, z})};
+            }
+        }
+    }
+}
+
+/// Summary column - NA when not computed
+pub fn outliers_cell(fq: &FqObject) -> String {
+    match &fq.outliers {
+        None => "NA".to_string(),
+        Some(outliers) if outliers.is_empty() => "none".to_string(),
+        Some(outliers) => outliers.iter().map(|x| format!("{} {}", x.metric, x.direction())).collect::<Vec<String>>().join("; "),
+    }
+}
+
+/// Print the files to look at
+pub fn print_outliers(input: &[FqObject]) {
+    let flagged: Vec<&FqObject> = input.iter().filter(|x| x.outliers.as_ref().map(|x| !x.is_empty()).unwrap_or(false)).collect();
+    let compared = input.iter().filter(|x| x.outliers.is_some()).count();
+    if compared == 0 {
+        return
+    }
+    if flagged.is_empty() {
+        println!("No outlier among {} files.", compared);
+        return
+    }
+    println!("Samples to look at ({} of {} files, robust z-score beyond {} against the median of all files):", flagged.len(), compared, MAX_Z);
+    for fq in flagged {
+        let details: Vec<String> = fq.outliers.iter().flatten()
+        .map(|x| format!("{} {} ({:.2} vs median {:.2}, z {:+.1})", x.metric, x.direction(), x.value, x.median, x.z))
+        .collect();
+        println!("  {}: {}", fq.sample, details.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Processed file with a median read quality and a size on disk
+    fn file(quality: f32, bytes: u64) -> FqObject {
+        let mut fq = FqObject::new(format!("lib_{}_{}.fq.gz", quality, bytes), 10);
+        fq.processed = true;
+        fq.qual_median = quality;
+        fq.file_bytes = bytes;
+        fq
+    }
+
+    #[test]
+    fn median_and_scaled_mad() {
+        let (median, mad) = median_mad(&[1.0, 2.0, 3.0, 4.0, 100.0]);
+        assert_eq!(median, 3.0);
+        assert!((mad - 1.4826).abs() < 1e-4);
+        // Identical values - MAD floor at 1% of the median
+        let (median, mad) = median_mad(&[10.0; 4]);
+        assert_eq!(median, 10.0);
+        assert!((mad - 0.1).abs() < 1e-6);
+    }
+
+    #[test]
+    fn flags_the_odd_file() {
+        let mut files = vec![file(35.0, 1000), file(35.2, 1010), file(34.9, 990), file(35.1, 1005), file(20.0, 100)];
+        flag_outliers(&mut files);
+        assert_eq!(outliers_cell(&files[0]), "none");
+        assert_eq!(outliers_cell(&files[4]), "quality low; yield low");
+    }
+
+    #[test]
+    fn needs_enough_files_and_the_same_compression() {
+        let mut files = vec![file(35.0, 1000), file(35.0, 1000), file(20.0, 100)];
+        flag_outliers(&mut files);
+        assert_eq!(outliers_cell(&files[2]), "NA");
+
+        let mut files = vec![file(35.0, 1000), file(35.0, 1000), file(35.0, 1000), file(35.0, 1000), file(35.0, 100)];
+        files[4].gzipped = true;
+        flag_outliers(&mut files);
+        assert_eq!(outliers_cell(&files[4]), "none");
+    }
+}
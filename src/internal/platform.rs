@@ -0,0 +1,241 @@
+use super::func::{histogram_quantile, histogram_percent_above};
+use super::illumina::parse_illumina_header;
+use super::nanopore::parse_nanopore_header;
+
+// SEQUENCING PLATFORMS
+// Detected from the read names first, then from the length distribution and quality alphabet
+// Illumina     @M00995:23:000000000-KWBNC:1:1101:14389:1740 1:N:0:15
+// Element      @AV223503:run:flowcell:1:10102:1234:5678 1:N:0:ACGT (Casava like, Q up to 50+)
+// MGI / BGI    @V300012345L1C001R0010000001/1
+// Ion Torrent  @ZV8K5:01188:01563
+// Nanopore     @uuid runid=... ch=... start_time=...
+// PacBio       @m64011_190830_220126/1234/ccs (HiFi) or @m54006_160504_020705/1234/0_5678 (CLR subreads)
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Platform {
+    Illumina,
+    Element,
+    MgiBgi,
+    IonTorrent,
+    Nanopore,
+    PacBioHiFi,
+    PacBioClr,
+    Unknown,
+}
+
+impl Platform {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Platform::Illumina => "Illumina",
+            Platform::Element => "Element",
+            Platform::MgiBgi => "MGI/BGI",
+            Platform::IonTorrent => "Ion Torrent",
+            Platform::Nanopore => "Oxford Nanopore",
+            Platform::PacBioHiFi => "PacBio HiFi",
+            Platform::PacBioClr => "PacBio CLR",
+            Platform::Unknown => "Unknown",
+        }
+    }
+
+    /// Platform from a command line value
+    pub fn from_name(name: &str) -> Option<Platform> {
+        match name.to_lowercase().as_str() {
+            "illumina" => Some(Platform::Illumina),
+            "element" | "aviti" => Some(Platform::Element),
+            "mgi" | "bgi" | "dnbseq" => Some(Platform::MgiBgi),
+            "iontorrent" | "ion-torrent" | "ion" => Some(Platform::IonTorrent),
+            "ont" | "nanopore" => Some(Platform::Nanopore),
+            "hifi" | "pacbio-hifi" | "ccs" => Some(Platform::PacBioHiFi),
+            "clr" | "pacbio-clr" => Some(Platform::PacBioClr),
+            "unknown" => Some(Platform::Unknown),
+            _ => None,
+        }
+    }
+
+    /// Long reads mode - binned quality charts, yield and length vs quality
+    pub fn is_long_reads(&self) -> bool {
+        matches!(self, Platform::Nanopore | Platform::PacBioHiFi | Platform::PacBioClr)
+    }
+
+    /// Read lengths vary a lot - positions at the end are covered by few reads
+    pub fn has_variable_length(&self) -> bool {
+        self.is_long_reads() || matches!(self, Platform::IonTorrent)
+    }
+
+    /// Read names carry lane, tile and index (Casava 1.8+)
+    pub fn has_illumina_headers(&self) -> bool {
+        matches!(self, Platform::Illumina | Platform::Element)
+    }
+}
+
+/// Read name of a single read -> platform, None if the format is not recognised
+pub fn platform_from_header(line: &str) -> Option<Platform> {
+    let name = line.strip_prefix('@').unwrap_or(line).split_whitespace().next().unwrap_or("");
+
+    if let Some(header) = parse_illumina_header(line) {
+        // Element AVITI instruments are AV.....
+        return Some(if header.instrument.starts_with("AV") {Platform::Element} else {Platform::Illumina})
+    }
+    if parse_nanopore_header(line).is_some() || line.split_whitespace().any(|x| is_uuid(x.strip_prefix('@').unwrap_or(x).split('/').next().unwrap_or(""))) {
+        return Some(Platform::Nanopore)
+    }
+    if let Some(platform) = pacbio_from_name(name) {
+        return Some(platform)
+    }
+    if is_mgi_name(name) {
+        return Some(Platform::MgiBgi)
+    }
+    // Older Illumina @HWUSI-EAS100R:6:73:941:1973#0/1
+    let fields: Vec<&str> = name.split(['#', '/']).next().unwrap_or("").split(':').collect();
+    if fields.len() == 5 && fields[1..].iter().all(|x| x.parse::<u32>().is_ok()) {
+        return Some(Platform::Illumina)
+    }
+    // Ion Torrent @ZV8K5:01188:01563
+    if fields.len() == 3 && fields[0].len() == 5 && fields[0].chars().all(|x| x.is_ascii_alphanumeric()) && fields[1..].iter().all(|x| x.len() == 5 && x.parse::<u32>().is_ok()) {
+        return Some(Platform::IonTorrent)
+    }
+    None
+}
+
+/// 8-4-4-4-12 hexadecimal read id
+fn is_uuid(name: &str) -> bool {
+    let parts: Vec<&str> = name.split('-').collect();
+    parts.len() == 5
+        && parts.iter().zip([8, 4, 4, 4, 12]).all(|(x, length)| x.len() == length && x.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// m64011_190830_220126/1234/ccs or m54006_160504_020705/1234/0_5678
+fn pacbio_from_name(name: &str) -> Option<Platform> {
+    let parts: Vec<&str> = name.split('/').collect();
+    if parts.len() < 2 || !parts[0].starts_with('m') || !parts[0].contains('_') || parts[1].parse::<u64>().is_err() {
+        return None
+    }
+    match parts.get(2) {
+        Some(x) if x.starts_with("ccs") => Some(Platform::PacBioHiFi),
+        Some(x) if x.split('_').all(|y| y.parse::<u64>().is_ok()) => Some(Platform::PacBioClr),
+        _ => Some(Platform::PacBioHiFi), // movie/zmw only - usually HiFi from SMRT Link
+    }
+}
+
+/// V300012345L1C001R0010000001/1 - flowcell, L lane, C column, R row, read number
+fn is_mgi_name(name: &str) -> bool {
+    let name = name.split('/').next().unwrap_or("");
+    let Some(lane_position) = name.find('L') else {return false};
+    let rest = &name[lane_position..];
+    let bytes = rest.as_bytes();
+    lane_position > 0
+        && rest.len() > 10
+        && bytes[1].is_ascii_digit()
+        && bytes[2] == b'C'
+        && bytes[3..6].iter().all(u8::is_ascii_digit)
+        && bytes[6] == b'R'
+        && bytes[7..].iter().all(u8::is_ascii_digit)
+}
+
+/// Quality encodings - Illumina 1.3 to 1.7 (and Solexa) wrote qualities with an offset of 64
+pub const PHRED33: &str = "Phred+33";
+pub const PHRED64: &str = "Phred+64";
+/// Difference between the two offsets
+pub const PHRED64_SHIFT: u32 = 31;
+
+/// Encoding of qualities read with an offset of 33
+/// Only when the characters prove the offset: nothing below '@' (Q0 + 64), above 'J' (Q41 + 33) and not above 'j' (Q42 + 64)
+/// High quality Phred+33 runs (PacBio HiFi, Element) reach Q42 - Q73 but always have some bases below Q31
+/// Solexa files (down to ';') are left as Phred+33
+pub fn detect_quality_encoding(qualities: &[Vec<u32>]) -> &'static str {
+    let min = qualities.iter().flatten().min().cloned().unwrap_or(0);
+    let max = qualities.iter().flatten().max().cloned().unwrap_or(0);
+    if min >= PHRED64_SHIFT && max > 41 && max <= 73 {PHRED64} else {PHRED33}
+}
+
+/// Guess the platform from a sample of read names, the read length histogram
+/// and the base quality histogram
+pub fn detect_platform(headers: &[String], length_histogram: &[u64], quality_histogram: &[u64]) -> Platform {
+    // 1 - Read names - majority vote
+    let mut votes: Vec<(Platform, usize)> = Vec::new();
+    for header in headers.iter().take(1000) {
+        let Some(platform) = platform_from_header(header) else {continue};
+        match votes.iter_mut().find(|(x, _)| *x == platform) {
+            Some(vote) => vote.1 += 1,
+            None => votes.push((platform, 1)),
+        }
+    }
+    let header_platform = votes.iter().max_by_key(|x| x.1).filter(|x| x.1 * 2 > headers.len().min(1000)).map(|x| x.0);
+
+    // 2 - Length distribution and quality alphabet
+    let total_reads: u64 = length_histogram.iter().sum();
+    let median_length = histogram_quantile(length_histogram, 0.5);
+    let max_length = length_histogram.len().saturating_sub(1);
+    let reads_at_max_length = length_histogram.last().cloned().unwrap_or(0);
+    let distinct_qualities = quality_histogram.iter().filter(|x| **x > 0).count();
+    let max_quality = quality_histogram.iter().rposition(|x| *x > 0).unwrap_or(0);
+    let percent_q20 = histogram_percent_above(quality_histogram, 20);
+
+    match header_platform {
+        // Casava names but qualities above what Illumina reports (Phred+64 files are converted before)
+        Some(Platform::Illumina) if max_quality > 42 => Platform::Element,
+        // HiFi and CLR subreads can be renamed - trust the qualities
+        Some(Platform::PacBioHiFi) if distinct_qualities <= 2 && max_quality == 0 => Platform::PacBioClr,
+        Some(platform) => platform,
+        None if median_length >= 1000.0 => {
+            if distinct_qualities <= 2 {Platform::PacBioClr} // no per base quality
+            else if distinct_qualities <= 8 && percent_q20 >= 90.0 {Platform::PacBioHiFi} // binned and high quality
+            else {Platform::Nanopore}
+        },
+        // Most reads trimmed to the same length - Illumina like
+        None if total_reads > 0 && max_length < 1000 && reads_at_max_length * 2 > total_reads => Platform::Illumina,
+        None => Platform::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Histogram with count at each of the values
+    fn histogram(values: &[usize], count: u64) -> Vec<u64> {
+        let mut histogram = vec![0; values.iter().max().map(|x| x + 1).unwrap_or(0)];
+        for value in values {histogram[*value] = count};
+        histogram
+    }
+
+    #[test]
+    fn platform_from_read_names() {
+        let lengths = histogram(&[151], 100);
+        let qualities = histogram(&[2, 12, 23, 37], 100);
+        let illumina = vec!["@M00995:23:000000000-KWBNC:1:1101:14389:1740 1:N:0:15".to_string(); 10];
+        assert_eq!(detect_platform(&illumina, &lengths, &qualities), Platform::Illumina);
+        let element = vec!["@AV223503:1:2324567890:1:10102:1234:5678 1:N:0:ACGT".to_string(); 10];
+        assert_eq!(detect_platform(&element, &lengths, &qualities), Platform::Element);
+        // Casava names with qualities up to Q45
+        assert_eq!(detect_platform(&illumina, &lengths, &histogram(&[5, 30, 45], 100)), Platform::Element);
+        let mgi = vec!["@V300012345L1C001R0010000001/1".to_string(); 10];
+        assert_eq!(detect_platform(&mgi, &lengths, &qualities), Platform::MgiBgi);
+        let nanopore = vec!["@0b7a1f5e-2f3b-4c55-9d1e-1b2c3d4e5f60 runid=abc ch=12".to_string(); 10];
+        assert_eq!(detect_platform(&nanopore, &lengths, &qualities), Platform::Nanopore);
+        assert_eq!(detect_platform(&["@m64011_190830_220126/1234/ccs".to_string()], &lengths, &qualities), Platform::PacBioHiFi);
+    }
+
+    #[test]
+    fn platform_from_lengths_and_qualities() {
+        let names = vec!["@read".to_string(); 10];
+        let long = histogram(&[800, 5000, 20000], 10);
+        assert_eq!(detect_platform(&names, &long, &histogram(&(0..40).collect::<Vec<usize>>(), 10)), Platform::Nanopore);
+        assert_eq!(detect_platform(&names, &long, &histogram(&[0], 10)), Platform::PacBioClr);
+        assert_eq!(detect_platform(&names, &long, &histogram(&[20, 30, 40, 93], 10)), Platform::PacBioHiFi);
+        assert_eq!(detect_platform(&names, &histogram(&[100, 150], 10), &histogram(&[30], 10)), Platform::Unknown);
+    }
+
+    #[test]
+    fn phred64_qualities() {
+        // Read with an offset of 33: Phred+64 Q2 to Q40 become 33 to 71
+        assert_eq!(detect_quality_encoding(&[vec![33, 50, 71]]), PHRED64);
+        assert_eq!(detect_quality_encoding(&[vec![2, 30, 41]]), PHRED33);
+        // Element and PacBio HiFi go above Q41 with low qualities or above 'j'
+        assert_eq!(detect_quality_encoding(&[vec![5, 30, 50]]), PHRED33);
+        assert_eq!(detect_quality_encoding(&[vec![30, 60, 93]]), PHRED33);
+        // HiFi like Q26 - Q60 sample stays Phred+33
+        assert_eq!(detect_quality_encoding(&[vec![26, 35, 48], vec![40, 60, 60], vec![29, 55]]), PHRED33);
+        assert_eq!(detect_quality_encoding(&[]), PHRED33);
+    }
+}
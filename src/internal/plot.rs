@@ -0,0 +1,311 @@
+use std::fmt::Write;
+use super::fastq::FqObject;
+use super::func::read_quality_histogram;
+use super::config::Figures;
+
+// FIGURES
+// The charts of the terminal report as data (Figure) so they can be rendered outside a terminal
+// figure_to_svg draws one figure as a standalone SVG with axes, ticks, labels and a title
+// PNG files are the same SVG rasterized with resvg
+
+const MARGIN_LEFT: f32 = 70.0;
+const MARGIN_RIGHT: f32 = 20.0;
+const MARGIN_TOP: f32 = 36.0;
+const MARGIN_BOTTOM: f32 = 50.0;
+/// Above this many bars, histograms are merged into wider bins
+const MAX_BARS: usize = 200;
+
+/// Base colors used by the content chart (A, C, G, T)
+pub const BASE_COLORS: [&str; 4] = ["#2ca02c", "#1f77b4", "#e6a700", "#d62728"];
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum SeriesKind {
+    Bars,
+    Line,
+}
+
+#[derive(Debug,Clone)]
+pub struct Series {
+    pub name: String,
+    pub color: &'static str,
+    pub kind: SeriesKind,
+    pub points: Vec<(f32, f32)>,
+}
+
+#[derive(Debug,Clone)]
+pub struct Figure {
+    pub id: &'static str, // used for file names and HTML anchors
+    pub title: String,
+    pub x_label: String,
+    pub y_label: String,
+    pub series: Vec<Series>,
+    pub y_range: Option<(f32, f32)>, // default is 0 to the highest point
+    pub threshold: Option<f32>, // dashed horizontal line
+}
+
+impl Figure {
+    fn new(id: &'static str, title: &str, x_label: &str, y_label: &str, kind: SeriesKind, points: Vec<(f32, f32)>) -> Self {
+        Self {
+            id,
+            title: title.to_string(),
+            x_label: x_label.to_string(),
+            y_label: y_label.to_string(),
+            series: vec![Series {name: String::new(), color: "#4a6fa5", kind, points}],
+            y_range: None,
+            threshold: None,
+        }
+    }
+}
+
+/// Merge (x, y) bars into at most max_bars bins of equal width, y is summed
+fn merge_bars(points: &[(f32, f32)], max_bars: usize) -> Vec<(f32, f32)> {
+    if points.len() <= max_bars {
+        return points.to_vec()
+    }
+    let x_min = points.first().map(|x| x.0).unwrap_or(0.0);
+    let x_max = points.last().map(|x| x.0).unwrap_or(0.0);
+    let width = ((x_max - x_min) / max_bars as f32).max(1.0);
+    let mut merged: Vec<(f32, f32)> = Vec::new();
+    for (x, y) in points {
+        let start = x_min + ((x - x_min) / width).floor() * width;
+        match merged.last_mut() {
+            Some(last) if last.0 == start => last.1 += y,
+            _ => merged.push((start, *y)),
+        }
+    }
+    merged
+}
+
+/// All figures of a processed file, in report order
+pub fn figures(fq: &FqObject) -> Vec<Figure> {
+    let mut figures: Vec<Figure> = Vec::new();
+
+    // Quality at each position
+    let min_reads = if fq.platform.has_variable_length() {fq.min_reads_per_bin} else {1};
+    let points: Vec<(f32, f32)> = fq.position_qualities.iter().filter(|x| x.2 >= min_reads).map(|x| (x.0 as f32, x.1)).collect();
+    let kind = if points.len() > MAX_BARS {SeriesKind::Line} else {SeriesKind::Bars};
+    let mut figure = Figure::new("quality_per_position", "Mean quality at each position", "Position (bp)", "Mean quality", kind, points);
+    figure.threshold = Some(20.0);
+    figures.push(figure);
+
+    // Long reads - quality from each end
+    for (id, title, x_label, profile) in [
+        ("quality_5_prime", "Mean quality from the 5' end", "Distance to the 5' end (bp)", &fq.five_prime_profile),
+        ("quality_3_prime", "Mean quality from the 3' end", "Distance to the 3' end (bp)", &fq.three_prime_profile),
+    ] {
+        let points: Vec<(f32, f32)> = profile.iter().filter(|x| x.2 >= fq.min_reads_per_bin).map(|x| (x.0 as f32, x.1)).collect();
+        if points.is_empty() {continue}
+        let mut figure = Figure::new(id, title, x_label, "Mean quality", SeriesKind::Line, points);
+        figure.threshold = Some(20.0);
+        figures.push(figure);
+    }
+
+    // Mean read quality
+    let points: Vec<(f32, f32)> = read_quality_histogram(&fq.read_mean_qualities).iter().map(|(q, reads)| (*q as f32, *reads as f32)).collect();
+    figures.push(Figure::new("mean_read_quality", "Distribution of mean read quality", "Mean read quality", "Reads", SeriesKind::Bars, points));
+
+    // Base quality
+    let points: Vec<(f32, f32)> = fq.quality_histogram.iter().enumerate().filter(|x| *x.1 > 0).map(|(q, bases)| (q as f32, *bases as f32)).collect();
+    figures.push(Figure::new("base_quality", "Number of bases with each Phred score", "Phred score", "Bases", SeriesKind::Bars, points));
+
+    // Length
+    let points: Vec<(f32, f32)> = fq.length_histogram.iter().enumerate().filter(|x| *x.1 > 0).map(|(length, reads)| (length as f32, *reads as f32)).collect();
+    figures.push(Figure::new("length", "Read length distribution", "Read length (bp)", "Reads", SeriesKind::Bars, merge_bars(&points, MAX_BARS)));
+
+    // Duplication
+    if fq.modules.duplication && !fq.duplication_histogram.is_empty() {
+        let points: Vec<(f32, f32)> = fq.duplication_histogram.iter().map(|(times, sequences)| (*times as f32, *sequences as f32)).collect();
+        figures.push(Figure::new("duplication", "Sequences duplicated x times (10 = 10 or more)", "Times seen", "Sequences", SeriesKind::Bars, points));
+    }
+
+    // Base composition
+    if fq.modules.content && fq.content.positions.len() > 1 {
+        let mut figure = Figure::new("content", "Base composition at each position", "Position (bp)", "% of bases", SeriesKind::Line, Vec::new());
+        figure.series = ["A", "C", "G", "T"].iter().enumerate().map(|(base, name)| Series {
+            name: name.to_string(),
+            color: BASE_COLORS[base],
+            kind: SeriesKind::Line,
+            points: fq.content.positions.iter().enumerate().map(|(position, x)| (position as f32 + 1.0, x[base])).collect(),
+        }).collect();
+        figure.y_range = Some((0.0, 100.0));
+        figures.push(figure);
+    }
+
+    // Complexity
+    if fq.modules.complexity && !fq.complexity.dust_histogram.is_empty() {
+        let points: Vec<(f32, f32)> = fq.complexity.dust_histogram.iter().enumerate().filter(|x| *x.1 > 0).map(|(score, reads)| (score as f32, *reads as f32)).collect();
+        figures.push(Figure::new("complexity", "DUST score (0 = random, 100 = homopolymer)", "DUST score", "Reads", SeriesKind::Bars, points));
+    }
+
+    figures.retain(|x| x.series.iter().any(|s| !s.points.is_empty()));
+    figures
+}
+
+/// Round steps to 1, 2 or 5 times a power of 10
+fn nice_step(range: f32, ticks: usize) -> f32 {
+    let raw = (range / ticks as f32).max(f32::MIN_POSITIVE);
+    let magnitude = 10f32.powf(raw.log10().floor());
+    let fraction = raw / magnitude;
+    let nice = if fraction <= 1.0 {1.0} else if fraction <= 2.0 {2.0} else if fraction <= 5.0 {5.0} else {10.0};
+    nice * magnitude
+}
+
+/// Tick label without useless decimals, large values in k / M
+pub fn format_tick(value: f32) -> String {
+    let absolute = value.abs();
+    if absolute >= 1e6 {
+        format!("{}M", format_tick(value / 1e6))
+    } else if absolute >= 1e4 {
+        format!("{}k", format_tick(value / 1e3))
+    } else if value.fract().abs() < 1e-3 {
+        format!("{}", value.round() as i64)
+    } else {
+        format!("{:.2}", value).trim_end_matches('0').trim_end_matches('.').to_string()
+    }
+}
+
+/// Escape text for XML / HTML
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Data ranges of a figure - x from the points, y from 0 unless a range is given
+pub fn figure_ranges(figure: &Figure) -> ((f32, f32), (f32, f32)) {
+    let points = figure.series.iter().flat_map(|x| x.points.iter());
+    let (mut x_min, mut x_max, mut y_max) = (f32::INFINITY, f32::NEG_INFINITY, 0f32);
+    for (x, y) in points {
+        x_min = x_min.min(*x);
+        x_max = x_max.max(*x);
+        y_max = y_max.max(*y);
+    }
+    if !x_min.is_finite() {(x_min, x_max) = (0.0, 1.0)};
+    // Room for the last bar
+    if figure.series.iter().any(|x| x.kind == SeriesKind::Bars) {
+        let count = figure.series.iter().map(|x| x.points.len()).max().unwrap_or(1).max(1);
+        x_max += ((x_max - x_min) / count as f32).max(1.0);
+    }
+    if x_max <= x_min {x_max = x_min + 1.0};
+    if let Some(threshold) = figure.threshold {y_max = y_max.max(threshold)};
+    let y_range = figure.y_range.unwrap_or((0.0, if y_max > 0.0 {y_max * 1.05} else {1.0}));
+    ((x_min, x_max), y_range)
+}
+
+/// Ticks between min and max
+pub fn ticks(min: f32, max: f32, count: usize) -> Vec<f32> {
+    let step = nice_step(max - min, count);
+    let mut tick = (min / step).ceil() * step;
+    let mut ticks: Vec<f32> = Vec::new();
+    while tick <= max + step * 1e-3 {
+        ticks.push(tick);
+        tick += step;
+    }
+    ticks
+}
+
+/// Standalone SVG of a figure
+pub fn figure_to_svg(figure: &Figure, width: u32, height: u32) -> String {
+    let (width, height) = (width as f32, height as f32);
+    let plot_width = width - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_height = height - MARGIN_TOP - MARGIN_BOTTOM;
+    let ((x_min, x_max), (y_min, y_max)) = figure_ranges(figure);
+    let x_to_pixel = |x: f32| MARGIN_LEFT + (x - x_min) / (x_max - x_min) * plot_width;
+    let y_to_pixel = |y: f32| MARGIN_TOP + plot_height - (y.clamp(y_min, y_max) - y_min) / (y_max - y_min) * plot_height;
+
+    let mut svg = String::new();
+    let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="Helvetica, Arial, sans-serif" font-size="12">"#, w = width, h = height);
+    let _ = writeln!(svg, r#"<rect width="{}" height="{}" fill="white"/>"#, width, height);
+    let _ = writeln!(svg, r#"<text x="{}" y="22" text-anchor="middle" font-size="15" font-weight="bold">{}</text>"#, width / 2.0, escape(&figure.title));
+
+    // Grid and ticks
+    for tick in ticks(y_min, y_max, 6) {
+        let y = y_to_pixel(tick);
+        let _ = writeln!(svg, r##"<line x1="{}" y1="{y:.1}" x2="{}" y2="{y:.1}" stroke="#e5e5e5"/>"##, MARGIN_LEFT, MARGIN_LEFT + plot_width);
+        let _ = writeln!(svg, r#"<text x="{}" y="{:.1}" text-anchor="end">{}</text>"#, MARGIN_LEFT - 6.0, y + 4.0, format_tick(tick));
+    }
+    for tick in ticks(x_min, x_max, 8) {
+        let x = x_to_pixel(tick);
+        let _ = writeln!(svg, r##"<line x1="{x:.1}" y1="{}" x2="{x:.1}" y2="{}" stroke="#333"/>"##, MARGIN_TOP + plot_height, MARGIN_TOP + plot_height + 5.0);
+        let _ = writeln!(svg, r#"<text x="{x:.1}" y="{}" text-anchor="middle">{}</text>"#, MARGIN_TOP + plot_height + 18.0, format_tick(tick));
+    }
+
+    // Data
+    for series in &figure.series {
+        match series.kind {
+            SeriesKind::Bars => {
+                let count = series.points.len().max(1) as f32;
+                let bar_width = (plot_width / count * 0.9).clamp(1.0, 40.0);
+                for (x, y) in &series.points {
+                    let top = y_to_pixel(*y);
+                    let _ = writeln!(svg, r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}"/>"#, x_to_pixel(*x), top, bar_width, MARGIN_TOP + plot_height - top, series.color);
+                }
+            },
+            SeriesKind::Line => {
+                let path: Vec<String> = series.points.iter().map(|(x, y)| format!("{:.1},{:.1}", x_to_pixel(*x), y_to_pixel(*y))).collect();
+                let _ = writeln!(svg, r#"<polyline fill="none" stroke="{}" stroke-width="1.5" points="{}"/>"#, series.color, path.join(" "));
+            },
+        }
+    }
+    if let Some(threshold) = figure.threshold {
+        let y = y_to_pixel(threshold);
+        let _ = writeln!(svg, r##"<line x1="{}" y1="{y:.1}" x2="{}" y2="{y:.1}" stroke="#d62728" stroke-dasharray="6,4"/>"##, MARGIN_LEFT, MARGIN_LEFT + plot_width);
+    }
+
+    // Axes, labels and legend
+    let _ = writeln!(svg, r##"<path d="M{l},{t} V{b} H{r}" fill="none" stroke="#333"/>"##, l = MARGIN_LEFT, t = MARGIN_TOP, b = MARGIN_TOP + plot_height, r = MARGIN_LEFT + plot_width);
+    let _ = writeln!(svg, r#"<text x="{}" y="{}" text-anchor="middle">{}</text>"#, MARGIN_LEFT + plot_width / 2.0, height - 12.0, escape(&figure.x_label));
+    let _ = writeln!(svg, r#"<text transform="translate(16,{}) rotate(-90)" text-anchor="middle">{}</text>"#, MARGIN_TOP + plot_height / 2.0, escape(&figure.y_label));
+    let named: Vec<&Series> = figure.series.iter().filter(|x| !x.name.is_empty()).collect();
+    for (i, series) in named.iter().enumerate() {
+        let x = MARGIN_LEFT + plot_width - 40.0 * (named.len() - i) as f32;
+        let _ = writeln!(svg, r#"<rect x="{:.1}" y="{}" width="10" height="10" fill="{}"/><text x="{:.1}" y="{}">{}</text>"#, x, MARGIN_TOP - 14.0, series.color, x + 14.0, MARGIN_TOP - 5.0, escape(&series.name));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Fonts tried for sans-serif, fontdb only knows Arial by default
+const SANS_SERIF_FONTS: [&str; 5] = ["Helvetica", "Arial", "Liberation Sans", "DejaVu Sans", "Noto Sans"];
+
+/// SVG rendering options with the system fonts loaded - load once and reuse for every PNG
+pub fn png_options() -> resvg::usvg::Options<'static> {
+    let mut options = resvg::usvg::Options::default();
+    let fonts = options.fontdb_mut();
+    fonts.load_system_fonts();
+    let installed: Vec<String> = fonts.faces().flat_map(|x| x.families.iter().map(|(name, _)| name.clone())).collect();
+    let sans_serif = SANS_SERIF_FONTS.iter().map(|x| x.to_string()).find(|x| installed.contains(x)).or(installed.first().cloned());
+    if let Some(family) = sans_serif {
+        fonts.set_sans_serif_family(family);
+    }
+    options
+}
+
+/// Rasterize an SVG to PNG bytes, dpi 96 keeps the SVG size
+pub fn svg_to_png(svg: &str, dpi: u32, options: &resvg::usvg::Options) -> Result<Vec<u8>, String> {
+    let tree = resvg::usvg::Tree::from_str(svg, options).map_err(|e| e.to_string())?;
+    let scale = dpi as f32 / 96.0;
+    let size = tree.size().to_int_size().scale_by(scale).ok_or("invalid figure size")?;
+    let mut pixmap = resvg::tiny_skia::Pixmap::new(size.width(), size.height()).ok_or("invalid figure size")?;
+    resvg::render(&tree, resvg::tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+    pixmap.encode_png().map_err(|e| e.to_string())
+}
+
+/// Write every figure of a file to <directory>/<sample>.<figure>.<format>, returns the paths written
+pub fn write_figures(fq: &FqObject, settings: &Figures, options: &resvg::usvg::Options) -> Result<Vec<String>, String> {
+    let directory = std::path::Path::new(&settings.directory);
+    std::fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+    let sample = &fq.sample;
+    let mut written: Vec<String> = Vec::new();
+    for figure in figures(fq) {
+        let svg = figure_to_svg(&figure, settings.width, settings.height);
+        for format in &settings.formats {
+            let path = directory.join(format!("{}.{}.{}", sample, figure.id, format));
+            let result = match format.as_str() {
+                "png" => std::fs::write(&path, svg_to_png(&svg, settings.dpi, options)?),
+                _ => std::fs::write(&path, &svg),
+            };
+            result.map_err(|e| e.to_string())?;
+            written.push(path.display().to_string());
+        }
+    }
+    Ok(written)
+}
@@ -0,0 +1,305 @@
+use std::{fs::File, io::{BufRead, BufReader}};
+use regex::Regex;
+use serde::Serialize;
+use super::fastq::FqObject;
+use super::func::{sample_name, sorted_quantile, histogram_quantile, histogram_percent_above, histogram_nx, pretty_print_int, sep};
+use super::config::Groups;
+
+// SAMPLE GROUPS (multi mode)
+// Lanes and technical replicates of a sample are grouped by Illumina file names (<sample>_S1_L001_R1_001),
+// a regex on the file name or a sheet listing the file of each sample
+// Each group gets metrics merged over its files and the lanes that differ from the others
+
+pub const GROUP_METHODS: [&str; 4] = ["none", "illumina", "regex", "sheet"];
+/// <sample>[_S<number>]_L<lane>[_<read>][_<chunk>] - R1 and R2 stay in different groups
+const ILLUMINA_FILE_NAME: &str = r"^(?P<sample>.+?)(?:_S\d+)?_(?P<lane>L\d{3})(?:_(?P<read>[RI]\d))?(?:_\d{3})?$";
+/// Largest difference between a lane and the median of its sample - quality in phred, length in % of the median, others in % points
+const LANE_TOLERANCES: [(&str, f32); 5] = [("quality", 2.0), ("q30", 5.0), ("gc", 2.0), ("duplication", 5.0), ("length", 10.0)];
+
+/// How files are grouped, built before processing so a bad regex or sheet stops early
+pub enum Grouping {
+    Pattern(Regex, bool), // true: Illumina file names, the read is added to the sample
+    Sheet(Vec<SheetRow>),
+}
+
+pub struct SheetRow {
+    pub file: String,
+    pub sample: String,
+    pub lane: Option<String>,
+}
+
+/// A sample and its merged metrics
+#[derive(Debug,Clone,Default,Serialize)]
+pub struct SampleGroup {
+    pub name: String,
+    pub files: Vec<String>,
+    pub lanes: Vec<String>,
+    pub reads: usize,
+    pub total_bases: u64,
+    pub len_mean: f32,
+    pub len_median: f32,
+    pub n50: u32,
+    pub qual_median: f32,
+    pub qual_mean: f32,
+    pub percent_q30: f32,
+    pub gc_percent: Option<f32>,
+    pub duplicated_percent: Option<f32>, // duplicates within each lane, weighted by reads
+    pub qual_median_spread: f32, // largest - smallest lane
+    pub percent_q30_spread: f32,
+    pub lane_flags: Vec<String>, // e.g. L002 quality low
+}
+
+/// Read the file / sample pairs of a CSV or tab separated sheet
+/// Needs "file" and "sample" columns (sample_id works too), "lane" is optional
+fn read_group_sheet(filepath: &str) -> Option<Vec<SheetRow>> {
+    let reader = BufReader::new(File::open(filepath).ok()?);
+    let lines: Vec<String> = reader.lines().map_while(Result::ok).filter(|x| !x.trim().is_empty()).collect();
+    let separator = if lines.first()?.contains('\t') {'\t'} else {','};
+    let header: Vec<String> = lines.first()?.split(separator).map(|x| x.trim().to_lowercase()).collect();
+    let file_column = header.iter().position(|x| x == "file")?;
+    let sample_column = header.iter().position(|x| x == "sample" || x == "sample_id")?;
+    let lane_column = header.iter().position(|x| x == "lane");
+    let rows = lines.iter().skip(1)
+    .map(|line| line.split(separator).map(|x| x.trim().to_string()).collect::<Vec<String>>())
+    .filter_map(|fields| Some(SheetRow {
+        file: fields.get(file_column).filter(|x| !x.is_empty())?.clone(),
+        sample: fields.get(sample_column).filter(|x| !x.is_empty())?.clone(),
+        lane: lane_column.and_then(|x| fields.get(x)).filter(|x| !x.is_empty()).cloned(),
+    }))
+    .collect();
+    Some(rows)
+}
+
+/// Grouping from the [groups] settings, None when files are not grouped
+pub fn build_grouping(settings: &Groups) -> Result<Option<Grouping>, String> {
+    match settings.by.as_str() {
+        "illumina" => Ok(Some(Grouping::Pattern(Regex::new(ILLUMINA_FILE_NAME).map_err(|e| e.to_string())?, true))),
+        "regex" => Ok(Some(Grouping::Pattern(Regex::new(&settings.regex).map_err(|e| e.to_string())?, false))),
+        "sheet" => match read_group_sheet(&settings.sheet) {
+            Some(rows) => Ok(Some(Grouping::Sheet(rows))),
+            None => Err(format!("could not read file and sample columns from {}", settings.sheet)),
+        },
+        _ => Ok(None),
+    }
+}
+
+/// Sample and lane of a file, the file is its own sample when it does not match
+fn file_group(filepath: &str, grouping: &Grouping) -> (String, String) {
+    let name = sample_name(filepath);
+    match grouping {
+        Grouping::Pattern(regex, illumina) => {
+            let Some(captures) = regex.captures(&name) else {return (name.clone(), name)};
+            // Named group "sample", or the first group, or the whole match
+            let sample = captures.name("sample").or(captures.get(1)).or(captures.get(0)).map(|x| x.as_str()).unwrap_or(&name);
+            let sample = match captures.name("read") {
+                Some(read) if *illumina => format!("{}_{}", sample, read.as_str()),
+                _ => sample.to_string(),
+            };
+            let lane = captures.name("lane").map(|x| x.as_str().to_string()).unwrap_or(name.clone());
+            (sample, lane)
+        },
+        Grouping::Sheet(rows) => {
+            let file_name = std::path::Path::new(filepath).file_name().and_then(|x| x.to_str()).unwrap_or(filepath);
+            match rows.iter().find(|row| row.file == filepath || row.file == file_name || sample_name(&row.file) == name) {
+                Some(row) => (row.sample.clone(), row.lane.clone().unwrap_or(name)),
+                None => (name.clone(), name),
+            }
+        },
+    }
+}
+
+/// Median of a few values
+fn median(values: &[f32]) -> f32 {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable_by(|a, b| a.total_cmp(b));
+    sorted_quantile(&sorted, 0.5)
+}
+
+/// Lane metrics compared to the other lanes, None when the module is disabled
+fn lane_metrics(fq: &FqObject) -> [Option<f32>; 5] {
+    [
+        Some(fq.qual_median),
+        Some(fq.percent_q30),
+        fq.modules.content.then_some(fq.content.gc_percent),
+        fq.modules.duplication.then_some(100.0 - fq.duplication_levels),
+        Some(fq.len_mean),
+    ]
+}
+
+/// Merged metrics of the files of a sample and lanes away from the others
+fn merge_group(name: String, files: &[&FqObject]) -> SampleGroup {
+    let reads: Vec<usize> = files.iter().map(|fq| fq.read_mean_qualities.len()).collect();
+    let total_reads: usize = reads.iter().sum();
+    let weighted = |values: Vec<f32>| values.iter().zip(&reads).map(|(x, n)| x * *n as f32).sum::<f32>() / total_reads.max(1) as f32;
+
+    // Histograms and read qualities add up
+    let mut length_histogram: Vec<u64> = vec![0; files.iter().map(|fq| fq.length_histogram.len()).max().unwrap_or(0)];
+    let mut quality_histogram: Vec<u64> = vec![0; 94];
+    for fq in files {
+        for (length, count) in fq.length_histogram.iter().enumerate() {length_histogram[length] += count};
+        for (q, count) in fq.quality_histogram.iter().enumerate().take(94) {quality_histogram[q] += count};
+    }
+    let mut qualities: Vec<f32> = files.iter().flat_map(|fq| fq.read_mean_qualities.iter().cloned()).collect();
+    qualities.sort_unstable_by(|a, b| a.total_cmp(b));
+    let total_bases: u64 = files.iter().map(|fq| fq.total_bases).sum();
+
+    let mut group = SampleGroup {
+        name,
+        files: files.iter().map(|fq| fq.filepath.clone()).collect(),
+        lanes: files.iter().map(|fq| fq.lane.clone().unwrap_or_default()).collect(),
+        reads: total_reads,
+        total_bases,
+        len_mean: (total_bases as f64 / total_reads.max(1) as f64) as f32,
+        len_median: histogram_quantile(&length_histogram, 0.5) as f32,
+        n50: histogram_nx(&length_histogram, 0.5).0 as u32,
+        qual_median: sorted_quantile(&qualities, 0.5),
+        qual_mean: weighted(files.iter().map(|fq| fq.qual_mean).collect()),
+        percent_q30: histogram_percent_above(&quality_histogram, 30),
+        gc_percent: None,
+        duplicated_percent: None,
+        qual_median_spread: 0.0,
+        percent_q30_spread: 0.0,
+        lane_flags: Vec::new(),
+    };
+    if files.iter().all(|fq| fq.modules.content) {
+        group.gc_percent = Some(files.iter().map(|fq| fq.content.gc_percent * fq.total_bases as f32).sum::<f32>() / total_bases.max(1) as f32);
+    }
+    if files.iter().all(|fq| fq.modules.duplication) {
+        group.duplicated_percent = Some(weighted(files.iter().map(|fq| 100.0 - fq.duplication_levels).collect()));
+    }
+
+    // Lane to lane consistency
+    let spread = |values: Vec<f32>| values.iter().cloned().fold(f32::MIN, f32::max) - values.iter().cloned().fold(f32::MAX, f32::min);
+    group.qual_median_spread = spread(files.iter().map(|fq| fq.qual_median).collect());
+    group.percent_q30_spread = spread(files.iter().map(|fq| fq.percent_q30).collect());
+    if files.len() < 2 {
+        return group
+    }
+    for (metric, (name, tolerance)) in LANE_TOLERANCES.iter().enumerate() {
+        let values: Vec<(usize, f32)> = files.iter().enumerate().filter_map(|(i, fq)| lane_metrics(fq)[metric].map(|x| (i, x))).collect();
+        let middle = median(&values.iter().map(|x| x.1).collect::<Vec<f32>>());
+        for (i, value) in values {
+            let difference = if *name == "length" {(value - middle) / middle.max(1.0) * 100.0} else {value - middle};
+            if difference.abs() > *tolerance {
+                group.lane_flags.push(format!("{} {} {}", group.lanes[i], name, if difference < 0.0 {"low"} else {"high"}));
+            }
+        }
+    }
+    group
+}
+
+/// Set the sample and lane of every processed file and merge each sample, in order of first file
+pub fn group_samples(input: &mut [FqObject], grouping: &Grouping) -> Vec<SampleGroup> {
+    let mut names: Vec<String> = Vec::new();
+    for fq in input.iter_mut().filter(|x| x.processed) {
+        let (sample, lane) = file_group(&fq.filepath, grouping);
+        if !names.contains(&sample) {names.push(sample.clone())};
+        fq.sample_group = Some(sample);
+        fq.lane = Some(lane);
+    }
+    names.into_iter()
+    .map(|name| {
+        let files: Vec<&FqObject> = input.iter().filter(|fq| fq.sample_group.as_ref() == Some(&name)).collect();
+        merge_group(name, &files)
+    })
+    .collect()
+}
+
+/// Summary column - lanes away from the others
+pub fn consistency_cell(group: &SampleGroup) -> String {
+    if group.files.len() < 2 {
+        "single file".to_string()
+    } else if group.lane_flags.is_empty() {
+        "consistent".to_string()
+    } else {
+        group.lane_flags.join("; ")
+    }
+}
+
+/// Column names and values of a sample
+pub fn sample_row(group: &SampleGroup) -> Vec<(String, String)> {
+    let optional = |value: Option<f32>| value.map(|x| format!("{:.2}", x)).unwrap_or("NA".to_string());
+    let row: Vec<(&str, String)> = vec![
+        ("Sample", group.name.clone()),
+        ("Files", group.files.len().to_string()),
+        ("Lanes", group.lanes.join(",")),
+        ("Reads Sampled", group.reads.to_string()),
+        ("Total Bases", group.total_bases.to_string()),
+        ("Average Length", format!("{:.2}", group.len_mean)),
+        ("Median Length", group.len_median.to_string()),
+        ("N50", group.n50.to_string()),
+        ("Median Quality", format!("{:.2}", group.qual_median)),
+        ("Average Quality", format!("{:.2}", group.qual_mean)),
+        ("% Bases >= Q30", format!("{:.2}", group.percent_q30)),
+        ("% GC", optional(group.gc_percent)),
+        ("% Duplicated", optional(group.duplicated_percent)),
+        ("Median Quality Spread", format!("{:.2}", group.qual_median_spread)),
+        ("% Q30 Spread", format!("{:.2}", group.percent_q30_spread)),
+        ("Lane Consistency", consistency_cell(group)),
+    ];
+    row.into_iter().map(|(name, value)| (name.to_string(), value)).collect()
+}
+
+/// Print the merged samples and their lane consistency
+pub fn print_samples(groups: &[SampleGroup]) {
+    if groups.is_empty() {
+        return
+    }
+    sep();
+    println!("{} SAMPLES FROM {} FILES", groups.len(), groups.iter().map(|x| x.files.len()).sum::<usize>());
+    sep();
+    println!("Sample\tLanes\tTotal Bases\tMedian Q\t% Q30\tQ30 Spread\tLane Consistency");
+    for group in groups {
+        println!("{}\t{}\t{}\t{:.2}\t{:.2}\t{:.2}\t{}", group.name, group.lanes.join(","), pretty_print_int(&(group.total_bases as usize)),
+            group.qual_median, group.percent_q30, group.percent_q30_spread, consistency_cell(group));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grouping(by: &str, regex: &str, sheet: &str) -> Grouping {
+        build_grouping(&Groups {by: by.to_string(), regex: regex.to_string(), sheet: sheet.to_string()}).unwrap().unwrap()
+    }
+
+    fn pair(sample: &str, lane: &str) -> (String, String) {
+        (sample.to_string(), lane.to_string())
+    }
+
+    #[test]
+    fn illumina_file_names() {
+        let illumina = grouping("illumina", "", "");
+        assert_eq!(file_group("/run/Foo_S1_L001_R1_001.fastq.gz", &illumina), pair("Foo_R1", "L001"));
+        assert_eq!(file_group("Foo_S1_L004_R2_001.fastq.gz", &illumina), pair("Foo_R2", "L004"));
+        assert_eq!(file_group("S1_L002_R1_001.fq.gz", &illumina), pair("S1_R1", "L002"));
+        // Sample names with underscores and no S number
+        assert_eq!(file_group("my_lib_2_L003_R1.fastq", &illumina), pair("my_lib_2_R1", "L003"));
+        assert_eq!(file_group("other.fq.gz", &illumina), pair("other", "other"));
+    }
+
+    #[test]
+    fn regex_groups() {
+        let named = grouping("regex", r"^(?P<sample>.+)_rep(?P<lane>\d)$", "");
+        assert_eq!(file_group("liver_rep2.fq.gz", &named), pair("liver", "2"));
+        let first = grouping("regex", r"^(\w+?)_", "");
+        assert_eq!(file_group("liver_a.fq.gz", &first), pair("liver", "liver_a"));
+        assert!(build_grouping(&Groups {by: "regex".to_string(), regex: "(".to_string(), sheet: String::new()}).is_err());
+        assert!(build_grouping(&Groups::default()).unwrap().is_none());
+    }
+
+    #[test]
+    fn sheet_groups() {
+        let path = std::env::temp_dir().join(format!("rsfqc_groups_{}.tsv", std::process::id()));
+        std::fs::write(&path, "Sample_ID\tFile\tLane\nliver\t/data/a.fq.gz\t1\n\nliver\tb.fastq.gz\t\nkidney\tc\t3\n").unwrap();
+        let sheet = grouping("sheet", "", path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        let Grouping::Sheet(rows) = &sheet else {panic!("not a sheet")};
+        assert_eq!(rows.len(), 3);
+        assert_eq!(file_group("/data/a.fq.gz", &sheet), pair("liver", "1"));
+        assert_eq!(file_group("/elsewhere/b.fastq.gz", &sheet), pair("liver", "b"));
+        assert_eq!(file_group("c.fq.gz", &sheet), pair("kidney", "3"));
+        assert_eq!(file_group("d.fq.gz", &sheet), pair("d", "d"));
+        assert!(read_group_sheet("/nonexistent/groups.csv").is_none());
+    }
+}
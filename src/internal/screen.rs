@@ -0,0 +1,289 @@
+use std::{fs::File, io::{BufRead, BufReader}, path::Path};
+use flate2::read::GzDecoder;
+use rayon::prelude::*;
+use super::func::get_encoding;
+
+// CONTAMINATION SCREEN
+// Like FastQ Screen but without an aligner: reads are compared to the k-mers of local FASTA references
+// Only k-mers whose hash is a multiple of "scaled" are kept (on both sides) to keep the index small
+// A read hits a reference if at least MIN_HITS of its kept k-mers are in that reference
+// References are read one record at a time and their k-mers merged into a sorted vector (16 bytes per k-mer kept),
+// so memory is about 16 x (distinct k-mers of all references) / scaled bytes, 1.5 times that while merging:
+// ~ 6 GB for a human genome at the default --scaled 8, 1.5 GB at 32 - halving scaled doubles it
+
+/// Up to 32 references (one bit each)
+pub const MAX_REFERENCES: usize = 32;
+/// Number of shared k-mers needed to call a hit
+pub const MIN_HITS: usize = 2;
+/// K-mers of a reference held before they are merged into the index (128 MB)
+const PENDING_KMERS: usize = 1 << 24;
+
+#[derive(Debug,Clone)]
+pub struct KmerIndex {
+    pub names: Vec<String>,
+    pub kmer_size: usize,
+    pub scaled: u64,
+    pub kmers: Vec<(u64, u32)>, // canonical k-mer and bit mask of references, sorted by k-mer
+}
+
+impl KmerIndex {
+    pub fn new(kmer_size: usize, scaled: u64) -> Self {
+        Self {names: Vec::new(), kmer_size, scaled, kmers: Vec::new()}
+    }
+
+    /// Bit mask of the references containing a k-mer
+    pub fn get(&self, kmer: u64) -> Option<u32> {
+        self.kmers.binary_search_by_key(&kmer, |x| x.0).ok().map(|i| self.kmers[i].1)
+    }
+}
+
+/// Result of the screen for one reference
+#[derive(Debug,Clone)]
+pub struct ScreenHit {
+    pub name: String,
+    pub unique: usize, // hit this reference only
+    pub multiple: usize, // hit this reference and at least another one
+}
+
+#[derive(Debug,Clone)]
+pub struct ScreenReport {
+    pub reads: usize,
+    pub no_hits: usize,
+    pub hits: Vec<ScreenHit>,
+}
+
+/// Mix the bits of a k-mer so that sampling is uniform (murmur3 finalizer)
+fn hash_kmer(kmer: u64) -> u64 {
+    let mut x = kmer;
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xff51afd7ed558ccd);
+    x ^= x >> 33;
+    x = x.wrapping_mul(0xc4ceb9fe1a85ec53);
+    x ^= x >> 33;
+    x
+}
+
+/// Canonical k-mers of a sequence (smallest of forward and reverse complement) that pass the sampling
+pub fn sampled_kmers(sequence: &[u8], kmer_size: usize, scaled: u64) -> Vec<u64> {
+    let mut kmers: Vec<u64> = Vec::new();
+    for_each_sampled_kmer(sequence, kmer_size, scaled, |kmer| kmers.push(kmer));
+    kmers
+}
+
+/// Call f on each sampled canonical k-mer of a sequence
+fn for_each_sampled_kmer(sequence: &[u8], kmer_size: usize, scaled: u64, mut f: impl FnMut(u64)) {
+    let mask: u64 = if kmer_size >= 32 {u64::MAX} else {(1 << (2 * kmer_size)) - 1};
+    let shift = 2 * (kmer_size as u64 - 1);
+    let mut forward: u64 = 0;
+    let mut reverse: u64 = 0;
+    let mut valid = 0; // number of ACGT in a row
+    for base in sequence {
+        let value = match base {b'A' | b'a' => 0, b'C' | b'c' => 1, b'G' | b'g' => 2, b'T' | b't' => 3, _ => {valid = 0; continue}};
+        forward = ((forward << 2) | value) & mask;
+        reverse = (reverse >> 2) | ((3 - value) << shift);
+        valid += 1;
+        if valid >= kmer_size {
+            let canonical = forward.min(reverse);
+            if hash_kmer(canonical).is_multiple_of(scaled) {f(canonical)};
+        }
+    }
+}
+
+/// Call f on each record of a (gzipped) FASTA file, returns the number of records
+fn read_fasta(filepath: &String, f: impl FnMut(&[u8])) -> Option<usize> {
+    let file = File::open(filepath).ok()?;
+    if get_encoding(filepath) {
+        for_each_record(BufReader::new(GzDecoder::new(file)), f)
+    } else {
+        for_each_record(BufReader::new(file), f)
+    }
+}
+
+/// Call f on each record of a FASTA, one at a time - lines starting with ';' are comments
+fn for_each_record(reader: impl BufRead, mut f: impl FnMut(&[u8])) -> Option<usize> {
+    let mut records = 0;
+    let mut sequence: Option<Vec<u8>> = None;
+    for line in reader.lines() {
+        let line = line.ok()?;
+        if line.starts_with(';') {
+            continue
+        } else if line.starts_with('>') {
+            if let Some(previous) = sequence.replace(Vec::new()) {f(&previous)};
+            records += 1;
+        } else if let Some(sequence) = sequence.as_mut() {
+            sequence.extend_from_slice(line.trim_end().as_bytes());
+        }
+    }
+    if let Some(last) = sequence {f(&last)};
+    Some(records)
+}
+
+/// Sequences of a FASTA, lines starting with ';' are comments
+pub fn parse_fasta(reader: impl BufRead) -> Option<Vec<Vec<u8>>> {
+    let mut sequences: Vec<Vec<u8>> = Vec::new();
+    for_each_record(reader, |x| sequences.push(x.to_vec()))?;
+    Some(sequences)
+}
+
+/// Name of a reference: name=path or the file name without extensions
+pub fn reference_name(reference: &str) -> (String, String) {
+    if let Some((name, path)) = reference.split_once('=') {
+        return (name.to_string(), path.to_string())
+    }
+    let file_name = Path::new(reference).file_name().and_then(|x| x.to_str()).unwrap_or(reference);
+    let name = file_name.split('.').next().unwrap_or(file_name);
+    (name.to_string(), reference.to_string())
+}
+
+/// Merge k-mers of the reference with this bit into the index, empties kmers
+fn merge_kmers(index: &mut KmerIndex, kmers: &mut Vec<u64>, bit: u32) {
+    kmers.par_sort_unstable();
+    kmers.dedup();
+    index.kmers.extend(kmers.drain(..).map(|x| (x, bit)));
+    // Two sorted runs - the stable sort merges them in one pass
+    index.kmers.sort_by_key(|x| x.0);
+    index.kmers.dedup_by(|later, kept| {
+        if later.0 != kept.0 {return false}
+        kept.1 |= later.1;
+        true
+    });
+}
+
+/// Add the k-mers of one reference to the index
+pub fn add_reference(index: &mut KmerIndex, name: String, sequences: &[Vec<u8>]) {
+    let bit = 1 << index.names.len();
+    let mut kmers: Vec<u64> = sequences.par_iter().flat_map(|x| sampled_kmers(x, index.kmer_size, index.scaled)).collect();
+    merge_kmers(index, &mut kmers, bit);
+    index.names.push(name);
+}
+
+/// Build the k-mer index of all references, the error is the path of the first unreadable or empty reference
+pub fn build_index(references: &[String], kmer_size: usize, scaled: u64) -> Result<KmerIndex, String> {
+    let mut index = KmerIndex::new(kmer_size, scaled);
+    for reference in references {
+        let (name, path) = reference_name(reference);
+        let bit = 1 << index.names.len();
+        let mut pending: Vec<u64> = Vec::new();
+        let records = read_fasta(&path, |sequence| {
+            for_each_sampled_kmer(sequence, kmer_size, scaled, |kmer| pending.push(kmer));
+            if pending.len() >= PENDING_KMERS {merge_kmers(&mut index, &mut pending, bit)};
+        });
+        if records.unwrap_or(0) == 0 {
+            return Err(path)
+        }
+        merge_kmers(&mut index, &mut pending, bit);
+        index.names.push(name);
+    }
+    Ok(index)
+}
+
+/// References hit by a read as a bit mask
+pub fn screen_read(index: &KmerIndex, sequence: &str) -> u32 {
+    let mut hits: [usize; MAX_REFERENCES] = [0; MAX_REFERENCES];
+    for kmer in sampled_kmers(sequence.as_bytes(), index.kmer_size, index.scaled) {
+        let Some(mask) = index.get(kmer) else {continue};
+        for (reference, count) in hits.iter_mut().enumerate().take(index.names.len()) {
+            if mask & (1 << reference) != 0 {*count += 1};
+        }
+    }
+    hits.iter().enumerate().filter(|(_, x)| **x >= MIN_HITS).fold(0, |acc, (reference, _)| acc | (1 << reference))
+}
+
+/// Screen all reads against the index
+pub fn screen_reads(index: &KmerIndex, sequences: &[String]) -> ScreenReport {
+    let masks: Vec<u32> = sequences.par_iter().map(|x| screen_read(index, x)).collect();
+    let hits: Vec<ScreenHit> = index.names.iter().enumerate().map(|(reference, name)| {
+        let bit = 1 << reference;
+        ScreenHit {
+            name: name.clone(),
+            unique: masks.iter().filter(|x| **x == bit).count(),
+            multiple: masks.iter().filter(|x| **x & bit != 0 && **x != bit).count(),
+        }
+    }).collect();
+    ScreenReport {
+        reads: sequences.len(),
+        no_hits: masks.iter().filter(|x| **x == 0).count(),
+        hits,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::barcodes::reverse_complement;
+
+    const REFERENCE_A: &str = "GATTTGGGGTTCAAAGCAGTATCGATCAAATAGTAAATCCATTTGTTCAACTCACAGTTTACGG";
+    const REFERENCE_B: &str = "TCAGCTTAGCCAGTCGATTGCCATGACTAGGCTAACCGTTGACAGTCCTAGGATCCATGCAAGT";
+
+    fn index(references: &[&str]) -> KmerIndex {
+        let mut index = KmerIndex::new(21, 1);
+        for (i, reference) in references.iter().enumerate() {
+            add_reference(&mut index, format!("ref{}", i), &[reference.as_bytes().to_vec()]);
+        }
+        index
+    }
+
+    #[test]
+    fn reverse_complement_gives_the_same_kmers() {
+        for scaled in [1, 4] {
+            let mut forward = sampled_kmers(REFERENCE_A.as_bytes(), 21, scaled);
+            let mut reverse = sampled_kmers(reverse_complement(REFERENCE_A).as_bytes(), 21, scaled);
+            forward.sort();
+            reverse.sort();
+            assert_eq!(forward, reverse);
+        }
+        assert_eq!(sampled_kmers(REFERENCE_A.as_bytes(), 21, 1).len(), REFERENCE_A.len() - 20);
+        // k-mers do not span an N
+        assert!(sampled_kmers(format!("{}N{}", &REFERENCE_A[..20], &REFERENCE_A[20..40]).as_bytes(), 21, 1).is_empty());
+    }
+
+    #[test]
+    fn hits_need_min_hits_kmers() {
+        let index = index(&[REFERENCE_A]);
+        // 21 bases share one k-mer, 22 bases share two
+        assert_eq!(screen_read(&index, &REFERENCE_A[10..31]), 0);
+        assert_eq!(screen_read(&index, &REFERENCE_A[10..32]), 1);
+        assert_eq!(screen_read(&index, &reverse_complement(&REFERENCE_A[10..40])), 1);
+        assert_eq!(MIN_HITS, 2);
+    }
+
+    #[test]
+    fn unique_and_multiple_hits() {
+        let index = index(&[REFERENCE_A, &format!("{}{}", &REFERENCE_A[..30], REFERENCE_B)]);
+        let reads: Vec<String> = [&REFERENCE_A[..30], &REFERENCE_A[30..], &REFERENCE_B[10..50], "ACGTACGTACGTACGTACGTACGTACGT"].iter().map(|x| x.to_string()).collect();
+        let report = screen_reads(&index, &reads);
+        assert_eq!(report.reads, 4);
+        assert_eq!(report.no_hits, 1);
+        assert_eq!((report.hits[0].unique, report.hits[0].multiple), (1, 1));
+        assert_eq!((report.hits[1].unique, report.hits[1].multiple), (1, 1));
+    }
+
+    #[test]
+    fn index_is_sorted_with_merged_masks() {
+        let index = index(&[REFERENCE_A, REFERENCE_A, REFERENCE_B]);
+        assert!(index.kmers.windows(2).all(|x| x[0].0 < x[1].0));
+        let kmer = sampled_kmers(REFERENCE_A.as_bytes(), 21, 1)[0];
+        assert_eq!(index.get(kmer), Some(0b011));
+        assert_eq!(index.kmers.len(), 2 * (64 - 20));
+    }
+
+    #[test]
+    fn parses_multi_record_fasta() {
+        let fasta = "; comment\n>one description\nacgt\nACGT\n\n>two\nGGCC\r\n>empty\n";
+        let sequences = parse_fasta(fasta.as_bytes()).unwrap();
+        assert_eq!(sequences, vec![b"acgtACGT".to_vec(), b"GGCC".to_vec(), Vec::new()]);
+        assert_eq!(sampled_kmers(b"acgtacgtacgtacgtacgtac", 21, 1), sampled_kmers(b"ACGTACGTACGTACGTACGTAC", 21, 1));
+    }
+
+    #[test]
+    fn empty_reference_is_an_error() {
+        let path = std::env::temp_dir().join(format!("rsfqc_empty_{}.fa", std::process::id()));
+        // Empty and 1 byte files have no record
+        for contents in ["", "A", "\x1f"] {
+            std::fs::write(&path, contents).unwrap();
+            assert!(build_index(&[path.to_str().unwrap().to_string()], 21, 1).is_err());
+        }
+        std::fs::remove_file(&path).unwrap();
+        assert!(build_index(&["/nonexistent/reference.fa".to_string()], 21, 1).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde_json::{Map, Value};
+use super::fastq::FqObject;
+use super::func::civil_from_days;
+use super::contaminants::format_percent;
+use super::verdict::verdict_name;
+use super::config::Summary;
+use super::outliers::outliers_cell;
+use super::samples::{SampleGroup, sample_row};
+
+// SUMMARY TABLE
+// One row per file, written in multi mode (or with the summary output format)
+// <prefix>.summary.<txt|csv|jsonl|md> - TSV, CSV, JSON Lines or a Markdown table
+// mode: overwrite (with a warning), append (rows added, header checked) or error if the file exists
+// With grouped files, <prefix>.samples.<extension> has one row per sample with the merged metrics
+
+pub const SUMMARY_FORMATS: [&str; 4] = ["tsv", "csv", "jsonl", "markdown"];
+pub const SUMMARY_MODES: [&str; 3] = ["overwrite", "append", "error"];
+
+/// File extension of a summary format
+fn extension(format: &str) -> &'static str {
+    match format {"csv" => "csv", "jsonl" => "jsonl", "markdown" => "md", _ => "txt"}
+}
+
+/// Path of the summary file
+pub fn summary_path(settings: &Summary) -> PathBuf {
+    PathBuf::from(format!("{}.summary.{}", settings.prefix, extension(&settings.format)))
+}
+
+/// Path of the sample table
+fn samples_path(settings: &Summary) -> PathBuf {
+    PathBuf::from(format!("{}.samples.{}", settings.prefix, extension(&settings.format)))
+}
+
+/// UTC time as 2024-01-31T12:00:00Z
+pub fn utc_timestamp() -> String {
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|x| x.as_secs()).unwrap_or(0);
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    let (year, month, day) = civil_from_days(days as i64);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, time / 3600, time % 3600 / 60, time % 60)
+}
+
+/// Column names and values of a processed file
+/// grouped: files were grouped into samples, Group and Lane are left empty for files without one
+pub fn summary_row(fq: &FqObject, timestamp: &str, grouped: bool) -> Vec<(String, String)> {
+    let control_reads: usize = fq.control_counts.iter().filter(|(control, _)| *control != 0).map(|(_, count)| count).sum();
+    let row: Vec<(&str, String)> = vec![
+        ("Sample", fq.sample.clone()),
+        ("File", fq.filepath.clone()),
+        ("Platform", fq.platform.name().to_string()),
+        ("Minimum Length", fq.len_min.to_string()),
+        ("Median Length", fq.len_median.to_string()),
+        ("Average Length", format!("{:.2}", fq.len_mean)),
+        ("Maximum Length", fq.len_max.to_string()),
+        ("Minimum Quality", format!("{:.2}", fq.qual_min)),
+        ("Median Quality", format!("{:.2}", fq.qual_median)),
+        ("Average Quality", format!("{:.2}", fq.qual_mean)),
+        ("Maximum Quality", format!("{:.2}", fq.qual_max)),
+        ("% Bases >= Q20", format!("{:.2}", fq.percent_q20)),
+        ("% Bases >= Q30", format!("{:.2}", fq.percent_q30)),
+        ("% Bases >= Q40", format!("{:.2}", fq.percent_q40)),
+        ("Quality Alphabet Size", fq.quality_alphabet.len().to_string()),
+        ("Total Bases", fq.total_bases.to_string()),
+        ("N50", fq.n50.to_string()),
+        ("% Duplicated", format!("{:.2}", 100.0 - fq.duplication_levels)),
+        ("% Low Complexity", format!("{:.2}", fq.complexity.low_complexity_percent)),
+        ("% PhiX", format_percent(fq.contaminants.phix_percent)),
+        ("% rRNA", format_percent(fq.contaminants.rrna_percent)),
+        ("Passing Filter Reads (N)", fq.reads_passing_filter.to_string()),
+        ("Filtered Reads (Y)", fq.reads_filtered.to_string()),
+        ("Control Reads", control_reads.to_string()),
+        ("Quality Verdict", verdict_name(fq.verdicts.quality).to_string()),
+        ("Length Verdict", verdict_name(fq.verdicts.length).to_string()),
+        ("Duplication Verdict", verdict_name(fq.verdicts.duplication).to_string()),
+        ("Content Verdict", verdict_name(fq.verdicts.content).to_string()),
+        ("Adapters Verdict", verdict_name(fq.verdicts.adapters).to_string()),
+        ("N Verdict", verdict_name(fq.verdicts.n).to_string()),
+        ("Worst Verdict", fq.verdicts.worst().name().to_string()),
+        ("Outliers", outliers_cell(fq)),
+        // Run metadata
+        ("Compression", if fq.gzipped {"gzip"} else {"none"}.to_string()),
+        ("Encoding", fq.quality_encoding.to_string()),
+        ("Records Sampled", fq.first_n_quality.len().to_string()),
+        ("rsFQC Version", env!("CARGO_PKG_VERSION").to_string()),
+        ("Timestamp", timestamp.to_string()),
+    ];
+    let mut row: Vec<(String, String)> = row.into_iter().map(|(name, value)| (name.to_string(), value)).collect();
+    // Sample and lane of grouped files
+    if grouped {
+        row.splice(2..2, [("Group".to_string(), fq.sample_group.clone().unwrap_or_default()), ("Lane".to_string(), fq.lane.clone().unwrap_or_default())]);
+    }
+    // One column per contaminant reference from rsfqc.toml
+    row.extend(fq.contaminants.custom.iter().map(|(name, percent)| (format!("% {}", name), format!("{:.2}", percent))));
+    row
+}
+
+/// CSV field, quoted when needed
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {format!("\"{}\"", value.replace('"', "\"\""))} else {value.to_string()}
+}
+
+/// Header line of a format (None for JSON Lines)
+fn header_line(columns: &[&str], format: &str) -> Option<String> {
+    match format {
+        "csv" => Some(columns.iter().map(|x| csv_field(x)).collect::<Vec<String>>().join(",")),
+        "jsonl" => None,
+        "markdown" => Some(format!("| {} |", columns.iter().map(|x| x.replace('|', "\\|")).collect::<Vec<String>>().join(" | "))),
+        _ => Some(columns.join("\t")),
+    }
+}
+
+/// Numbers stay numbers in JSON Lines
+fn json_value(value: &str) -> Value {
+    if let Ok(integer) = value.parse::<i64>() {
+        return Value::from(integer)
+    }
+    match value.parse::<f64>().ok().and_then(serde_json::Number::from_f64) {
+        Some(number) => Value::Number(number),
+        None => Value::String(value.to_string()),
+    }
+}
+
+/// A row as a line of a format
+fn row_line(row: &[(String, String)], format: &str) -> String {
+    match format {
+        "csv" => row.iter().map(|(_, value)| csv_field(value)).collect::<Vec<String>>().join(","),
+        "jsonl" => {
+            let object: Map<String, Value> = row.iter().map(|(name, value)| (name.clone(), json_value(value))).collect();
+            Value::Object(object).to_string()
+        },
+        "markdown" => format!("| {} |", row.iter().map(|(_, value)| value.replace('|', "\\|")).collect::<Vec<String>>().join(" | ")),
+        _ => row.iter().map(|(_, value)| value.as_str()).collect::<Vec<&str>>().join("\t"),
+    }
+}
+
+/// Write the summary of all processed files, returns the path written
+pub fn write_summary(input: &[FqObject], settings: &Summary) -> Result<PathBuf, String> {
+    let timestamp = utc_timestamp();
+    let grouped = input.iter().any(|x| x.sample_group.is_some());
+    let rows: Vec<Vec<(String, String)>> = input.iter().filter(|x| x.processed).map(|fq| summary_row(fq, &timestamp, grouped)).collect();
+    write_rows(summary_path(settings), &rows, settings)
+}
+
+/// Write the merged metrics of grouped samples, returns the path written
+pub fn write_samples(groups: &[SampleGroup], settings: &Summary) -> Result<PathBuf, String> {
+    let rows: Vec<Vec<(String, String)>> = groups.iter().map(sample_row).collect();
+    write_rows(samples_path(settings), &rows, settings)
+}
+
+/// Write rows in the summary format and mode
+fn write_rows(path: PathBuf, rows: &[Vec<(String, String)>], settings: &Summary) -> Result<PathBuf, String> {
+    let columns: Vec<&str> = rows.first().map(|row| row.iter().map(|x| x.0.as_str()).collect()).unwrap_or_default();
+    let header = header_line(&columns, &settings.format);
+
+    let exists = path.is_file() && std::fs::metadata(&path).map(|x| x.len() > 0).unwrap_or(false);
+    let append = exists && settings.mode == "append";
+    if exists {
+        match settings.mode.as_str() {
+            "error" => return Err(format!("{} already exists (summary mode is error)", path.display())),
+            "append" => {
+                // Appending under a different header would shift the columns
+                let first_line = std::fs::read_to_string(&path).map_err(|e| e.to_string())?.lines().next().unwrap_or("").to_string();
+                if let Some(header) = &header {
+                    if first_line != *header {
+                        return Err(format!("{} has different columns, cannot append", path.display()))
+                    }
+                }
+            },
+            _ => println!("Overwriting {}", path.display()),
+        }
+    }
+
+    if let Some(parent) = Path::new(&path).parent().filter(|x| !x.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut text = String::new();
+    if !append {
+        if let Some(header) = header {
+            text.push_str(&header);
+            text.push('\n');
+            if settings.format == "markdown" {
+                text.push_str(&format!("|{}\n", "---|".repeat(columns.len())));
+            }
+        }
+    }
+    for row in rows {
+        text.push_str(&row_line(row, &settings.format));
+        text.push('\n');
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).write(true).append(append).truncate(!append).open(&path).map_err(|e| e.to_string())?;
+    file.write_all(text.as_bytes()).map_err(|e| e.to_string())?;
+    Ok(path)
+}
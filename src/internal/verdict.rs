@@ -0,0 +1,167 @@
+use std::io::IsTerminal;
+use serde::{Deserialize, Serialize};
+use super::platform::Platform;
+
+// PASS / WARN / FAIL
+// One verdict per module from thresholds with FastQC-like defaults
+// Thresholds can be changed in the [thresholds] section of rsfqc.toml or with a TOML file (--thresholds),
+// missing keys keep their default:
+//   quality_warn = 27.0
+//   duplication_fail = 50.0
+// Quality thresholds are for short reads, they are scaled down for noisy long reads (Nanopore, PacBio CLR)
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Default,Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Verdict {
+    #[default]
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl Verdict {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Verdict::Pass => "PASS",
+            Verdict::Warn => "WARN",
+            Verdict::Fail => "FAIL",
+        }
+    }
+
+    /// Name in green, yellow or red when printing to a terminal
+    pub fn colored(&self) -> String {
+        if !std::io::stdout().is_terminal() {
+            return self.name().to_string()
+        }
+        let color = match self {
+            Verdict::Pass => 32,
+            Verdict::Warn => 33,
+            Verdict::Fail => 31,
+        };
+        format!("\x1b[1;{}m{}\x1b[0m", color, self.name())
+    }
+
+    /// Exit code with --verdict-exit (1 is used for errors)
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Verdict::Pass => 0,
+            Verdict::Warn => 2,
+            Verdict::Fail => 3,
+        }
+    }
+}
+
+#[derive(Debug,Clone,Deserialize,Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Thresholds {
+    pub quality_warn: f32, // median of the mean read qualities below this
+    pub quality_fail: f32,
+    pub length_fail_below: u32, // any read shorter than this
+    pub length_warn_variable: bool, // read lengths differ on a fixed length platform
+    pub duplication_warn: f32, // % duplicated reads above this
+    pub duplication_fail: f32,
+    pub content_warn: f32, // largest A/T or G/C difference at a position above this
+    pub content_fail: f32,
+    pub adapters_warn: f32, // % reads with an adapter above this
+    pub adapters_fail: f32,
+    pub n_warn: f32, // largest % N at a position above this
+    pub n_fail: f32,
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            quality_warn: 27.0,
+            quality_fail: 20.0,
+            length_fail_below: 1,
+            length_warn_variable: true,
+            duplication_warn: 20.0,
+            duplication_fail: 50.0,
+            content_warn: 10.0,
+            content_fail: 20.0,
+            adapters_warn: 5.0,
+            adapters_fail: 10.0,
+            n_warn: 5.0,
+            n_fail: 20.0,
+        }
+    }
+}
+
+/// Noisy long reads are held to Q10 / Q7.4 with the default thresholds
+const LONG_READ_QUALITY_SCALE: f32 = 10.0 / 27.0;
+
+impl Thresholds {
+    /// Quality (warn, fail) thresholds for a platform
+    pub fn quality(&self, platform: Platform) -> (f32, f32) {
+        let scale = match platform {
+            Platform::Nanopore | Platform::PacBioClr => LONG_READ_QUALITY_SCALE,
+            _ => 1.0,
+        };
+        (self.quality_warn * scale, self.quality_fail * scale)
+    }
+}
+
+/// None when the module is disabled in rsfqc.toml
+#[derive(Debug,Clone,Default,Serialize)]
+pub struct Verdicts {
+    pub quality: Option<Verdict>,
+    pub length: Option<Verdict>,
+    pub duplication: Option<Verdict>,
+    pub content: Option<Verdict>,
+    pub adapters: Option<Verdict>,
+    pub n: Option<Verdict>,
+}
+
+impl Verdicts {
+    /// Module names and verdicts in report order
+    pub fn list(&self) -> [(&'static str, Option<Verdict>); 6] {
+        [
+            ("Quality", self.quality),
+            ("Length", self.length),
+            ("Duplication", self.duplication),
+            ("Content", self.content),
+            ("Adapters", self.adapters),
+            ("N", self.n),
+        ]
+    }
+
+    pub fn worst(&self) -> Verdict {
+        self.list().iter().filter_map(|x| x.1).max().unwrap_or_default()
+    }
+}
+
+/// Verdict name or NA for a disabled module
+pub fn verdict_name(verdict: Option<Verdict>) -> &'static str {
+    verdict.map(|x| x.name()).unwrap_or("NA")
+}
+
+/// Read a thresholds TOML file, unknown keys are an error
+pub fn read_thresholds(filepath: &String) -> Result<Thresholds, String> {
+    let text = std::fs::read_to_string(filepath).map_err(|e| e.to_string())?;
+    toml::from_str(&text).map_err(|e| e.to_string())
+}
+
+/// Verdict when a high value is bad
+pub fn verdict_above(value: f32, warn: f32, fail: f32) -> Verdict {
+    if value > fail {Verdict::Fail} else if value > warn {Verdict::Warn} else {Verdict::Pass}
+}
+
+/// Verdict when a low value is bad
+pub fn verdict_below(value: f32, warn: f32, fail: f32) -> Verdict {
+    if value < fail {Verdict::Fail} else if value < warn {Verdict::Warn} else {Verdict::Pass}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quality_thresholds_scaled_for_noisy_long_reads() {
+        let thresholds = Thresholds::default();
+        assert_eq!(thresholds.quality(Platform::Illumina), (27.0, 20.0));
+        assert_eq!(thresholds.quality(Platform::PacBioHiFi), (27.0, 20.0));
+        let (warn, fail) = thresholds.quality(Platform::Nanopore);
+        assert!((warn - 10.0).abs() < 1e-4 && fail < warn);
+        assert_eq!(verdict_below(12.3, warn, fail), Verdict::Pass);
+    }
+}
@@ -1,9 +1,23 @@
 #![allow(non_snake_case)]
-use std::{env, usize};
+use std::{env, path::PathBuf, sync::Arc};
 // Inside crates
 mod internal;
 use crate::internal::fastq::FqObject;
 use crate::internal::func::{*};
+use crate::internal::options::parse_arguments;
+use crate::internal::barcodes::read_sample_sheet;
+use crate::internal::screen::{build_index, MAX_REFERENCES};
+use crate::internal::verdict::{read_thresholds, Verdict};
+use crate::internal::config::{Config, find_config, read_config, check_summary, check_groups};
+use crate::internal::json::write_json_report;
+use crate::internal::html::{write_html_report, write_html_index};
+use crate::internal::plot::{png_options, write_figures};
+use crate::internal::fastqc::write_fastqc;
+use crate::internal::multiqc::write_multiqc;
+use crate::internal::summary::{write_summary, write_samples};
+use crate::internal::outliers::{flag_outliers, print_outliers};
+use crate::internal::comparison::comparison_charts;
+use crate::internal::samples::{build_grouping, group_samples, print_samples};
 
 fn main() {
 
@@ -14,54 +28,257 @@ fn main() {
     let mut args: Vec<String> = env::args().collect();
 
     // Capture the executable location
-    let _executable_location = args.get(0).unwrap().to_owned();
+    let _executable_location = args.first().unwrap().to_owned();
 
     // Test if there is any argument used
     args.remove(0); // remove first argument since it's the location of the executable 
-    if args.len() == 0 {
+    if args.is_empty() {
         println!("No input");
         std::process::exit(1);
     };
+    let options = parse_arguments(args);
+
+    // Expected indices
+    let expected_indices = match &options.sample_sheet {
+        Some(path) => match read_sample_sheet(path) {
+            Some(indices) => indices,
+            None => {
+                println!("Could not read indices from sample sheet {}", path);
+                std::process::exit(1);
+            }
+        },
+        None => Vec::new(),
+    };
+
+    // Configuration file
+    let config_path = match &options.config {
+        Some(path) => Some(PathBuf::from(path)),
+        None => find_config(),
+    };
+    let mut config = match &config_path {
+        Some(path) => match read_config(path) {
+            Ok(config) => config,
+            Err(error) => {
+                println!("Could not read configuration file {}: {}", path.display(), error);
+                std::process::exit(1);
+            }
+        },
+        None => Config::default(),
+    };
+
+    if options.json && !config.has_format("json") {config.output.formats.push("json".to_string())};
+    if options.html && !config.has_format("html") {config.output.formats.push("html".to_string())};
+    if options.figures && !config.has_format("figures") {config.output.formats.push("figures".to_string())};
+    if options.fastqc && !config.has_format("fastqc") {config.output.formats.push("fastqc".to_string())};
+    if options.fastqc_zip {config.output.fastqc_zip = true};
+    if options.multiqc && !config.has_format("multiqc") {config.output.formats.push("multiqc".to_string())};
+    if let Some(format) = &options.summary_format {config.summary.format = format.clone()};
+    if let Some(prefix) = &options.summary_prefix {config.summary.prefix = prefix.clone()};
+    if options.append {config.summary.mode = "append".to_string()};
+    if let Err(error) = check_summary(&config.summary) {
+        println!("{}", error);
+        std::process::exit(1);
+    }
+    if options.group_lanes {config.groups.by = "illumina".to_string()};
+    if let Some(regex) = &options.group_regex {
+        config.groups.by = "regex".to_string();
+        config.groups.regex = regex.clone();
+    }
+    if let Some(sheet) = &options.group_sheet {
+        config.groups.by = "sheet".to_string();
+        config.groups.sheet = sheet.clone();
+    }
+    let grouping = match check_groups(&config.groups).and_then(|_| build_grouping(&config.groups)) {
+        Ok(grouping) => grouping,
+        Err(error) => {
+            println!("Could not group files: {}", error);
+            std::process::exit(1);
+        }
+    };
+
+    // PASS / WARN / FAIL thresholds from the command line replace the configuration file
+    if let Some(path) = &options.thresholds {
+        match read_thresholds(path) {
+            Ok(thresholds) => config.thresholds = thresholds,
+            Err(error) => {
+                println!("Could not read thresholds from {}: {}", path, error);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Extra contaminants from the configuration file
+    let custom_contaminants = if config.contaminants.is_empty() || !config.modules.contaminants {None} else {
+        match build_index(&config.contaminants, 21, 8) {
+            Ok(index) => Some(Arc::new(index)),
+            Err(path) => {
+                println!("Could not read contaminant reference {}", path);
+                std::process::exit(1);
+            }
+        }
+    };
 
     // The number of records to test
-    let number_of_records_to_test: usize = 100000;
+    let number_of_records_to_test: usize = config.records;
 
-    // CHART WIDTH
-    let _chart_width = 140 as u32;
-    let _chart_height = 60 as u32;
+    // Thread budget - the global pool is used in single mode and by the screen subcommand
+    if let Some(threads) = options.threads {config.threads = threads};
+    let threads = thread_count(config.threads);
+    rayon::ThreadPoolBuilder::new().num_threads(threads).build_global().unwrap();
 
     //single of multi mode
     let mut multi_mode: bool = false;
 
     // Get the list of Files to process
     let mut all_fq_to_process: Vec<FqObject> = Vec::new();
-    for path in args {
+    for path in options.files {
         let mut new_fq = FqObject::new(path, number_of_records_to_test);
+        new_fq.expected_indices = expected_indices.clone();
+        new_fq.exclude_filtered = options.exclude_filtered;
+        new_fq.platform_override = options.platform;
+        new_fq.thresholds = config.thresholds.clone();
+        new_fq.modules = config.modules.clone();
+        new_fq.adapter_list = config.adapters.clone();
+        new_fq.custom_contaminants = custom_contaminants.clone();
+        new_fq.plot_width = config.plot.width;
+        new_fq.plot_height = config.plot.height;
+        new_fq.end_profile_length = config.long_reads.end_profile_length;
+        new_fq.min_reads_per_bin = config.long_reads.min_reads_per_bin;
         new_fq.pre_process();
         if new_fq.isFastq & new_fq.isFile {all_fq_to_process.push(new_fq)}
     }
 
     // If no valid FastQ detected
-    if all_fq_to_process.len() == 0 {
+    if all_fq_to_process.is_empty() {
         println!("No valid input detected.");
         std::process::exit(1);
     };
 
+    // Reports are named after the files, two inputs must not write the same ones
+    unique_sample_names(&mut all_fq_to_process);
+
+    // Contamination screen subcommand
+    if options.screen {
+        if options.references.is_empty() || options.references.len() > MAX_REFERENCES {
+            println!("screen needs between 1 and {} references (-r reference.fa)", MAX_REFERENCES);
+            std::process::exit(1);
+        }
+        println!("Indexing {} references (k = {}, keeping 1 k-mer in {})", options.references.len(), options.kmer_size, options.scaled);
+        let index = match build_index(&options.references, options.kmer_size, options.scaled) {
+            Ok(index) => index,
+            Err(path) => {
+                println!("Could not read reference {}", path);
+                std::process::exit(1);
+            }
+        };
+        println!("Indexed {} k-mers", pretty_print_int(&index.kmers.len()));
+        for mut fq in all_fq_to_process {
+            fq.process_screen(&index);
+        }
+        std::process::exit(0);
+    }
+
     // Check the number of Fastq file to process
     if all_fq_to_process.len() > 1 {multi_mode = true;println!("Found {} valid FastQ files.", all_fq_to_process.len())};
 
     // Process all Fastq Files
     let mut new_holder: Vec<FqObject> = Vec::new();
+    let mut samples = Vec::new();
     if multi_mode {
-        println!("Processing.");
-        for mut fq in  all_fq_to_process {
-            fq.process_multi();
-            new_holder.push(fq)}
-            println!("Processed {} FastQ files into {}/rsFQC.summary.txt", new_holder.len(), env::current_dir().unwrap().to_str().unwrap());
-        write_reports(new_holder);
+        println!("Processing with {} threads.", threads);
+        new_holder = process_files(all_fq_to_process, threads);
+        flag_outliers(&mut new_holder);
+        if let Some(grouping) = &grouping {samples = group_samples(&mut new_holder, grouping)};
+        if config.has_format("text") {comparison_charts(&new_holder, config.plot.width, config.plot.height)};
     } else{
-        all_fq_to_process.get(0).unwrap().to_owned().process_single();
+        let mut fq = all_fq_to_process.first().unwrap().to_owned();
+        if config.has_format("text") {fq.process_single()} else {fq.process_multi()};
+        new_holder.push(fq);
+    }
+    if config.has_format("json") {
+        for fq in &new_holder {
+            match write_json_report(fq) {
+                Ok(path) => println!("JSON report written to {}", path),
+                Err(error) => {
+                    println!("Could not write the JSON report of {}: {}", fq.filepath, error);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+    if config.has_format("html") {
+        for fq in &new_holder {
+            match write_html_report(fq) {
+                Ok(path) => println!("HTML report written to {}", path),
+                Err(error) => {
+                    println!("Could not write the HTML report of {}: {}", fq.filepath, error);
+                    std::process::exit(1);
+                }
+            }
+        }
+        if multi_mode {
+            match write_html_index(&new_holder) {
+                Ok(path) => println!("HTML index written to {}", path),
+                Err(error) => {
+                    println!("Could not write the HTML index: {}", error);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+    if config.has_format("figures") {
+        let png = png_options();
+        for fq in &new_holder {
+            match write_figures(fq, &config.figures, &png) {
+                Ok(paths) => println!("{} figures of {} written to {}", paths.len(), fq.filepath, config.figures.directory),
+                Err(error) => {
+                    println!("Could not write the figures of {}: {}", fq.filepath, error);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+    if config.has_format("fastqc") {
+        for fq in &new_holder {
+            match write_fastqc(fq, config.output.fastqc_zip) {
+                Ok(paths) => println!("FastQC data written to {}", paths.join(" and ")),
+                Err(error) => {
+                    println!("Could not write the FastQC data of {}: {}", fq.filepath, error);
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+    if config.has_format("multiqc") {
+        match write_multiqc(&new_holder) {
+            Ok(paths) => println!("MultiQC custom content written to {}", paths.join(", ")),
+            Err(error) => {
+                println!("Could not write the MultiQC custom content: {}", error);
+                std::process::exit(1);
+            }
+        }
+    }
+    let worst_verdict = new_holder.iter().map(|fq| fq.verdicts.worst()).max().unwrap_or(Verdict::Pass);
+    if multi_mode || config.has_format("summary") {
+        match write_summary(&new_holder, &config.summary) {
+            Ok(path) => println!("Processed {} FastQ files into {}", new_holder.len(), env::current_dir().unwrap().join(path).display()),
+            Err(error) => {
+                println!("Could not write the summary: {}", error);
+                std::process::exit(1);
+            }
+        }
+    };
+    if !samples.is_empty() {
+        print_samples(&samples);
+        match write_samples(&samples, &config.summary) {
+            Ok(path) => println!("Merged {} samples into {}", samples.len(), env::current_dir().unwrap().join(path).display()),
+            Err(error) => {
+                println!("Could not write the sample table: {}", error);
+                std::process::exit(1);
+            }
+        }
     }
+    if multi_mode {print_outliers(&new_holder)};
 
 
     // FASTQ FORMAT
@@ -71,6 +288,8 @@ fn main() {
     // !''*((((***+))%%%++)(%%%%).1***-+*''))**55CCF>>>>>>CCCCCCC65
 
     // // EXIT CODES
+    // 0 OK, 1 error, 2 WARN and 3 FAIL with --verdict-exit
+    if options.verdict_exit {std::process::exit(worst_verdict.exit_code())};
 
     std::process::exit(0);
 